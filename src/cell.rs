@@ -0,0 +1,231 @@
+use std::cell::Cell;
+
+use crate::{Element, UnionFindError, UnionOutcome};
+
+/// A [`CellUnionFind`] is a [`UnionFind`](crate::UnionFind) whose elements live
+/// in [`Cell`]s, so that [`CellUnionFind::find`] can compress paths and
+/// [`CellUnionFind::union`] can link sets through a shared reference.
+///
+/// It always uses full path compression and links by rank.
+pub struct CellUnionFind {
+    backing: Vec<Cell<Element>>,
+    set_count: Cell<usize>,
+}
+
+impl CellUnionFind {
+    /// Construct a new [`CellUnionFind`] with the given number of initial elements,
+    /// each in their own set.
+    pub fn new(size: usize) -> Self {
+        CellUnionFind {
+            backing: (0..size)
+                .map(|i| {
+                    Cell::new(Element {
+                        parent: i,
+                        rank: 0,
+                        size: 1,
+                        next: i,
+                    })
+                })
+                .collect(),
+            set_count: Cell::new(size),
+        }
+    }
+
+    /// Add a fresh element into the union find structure.
+    pub fn fresh(&mut self) -> usize {
+        let fresh = self.backing.len();
+        self.backing.push(Cell::new(Element {
+            parent: fresh,
+            rank: 0,
+            size: 1,
+            next: fresh,
+        }));
+        self.set_count.set(self.set_count.get() + 1);
+        fresh
+    }
+
+    /// Find the representative for the set that this element belongs to.
+    pub fn find(&self, element_id: usize) -> Option<usize> {
+        if element_id >= self.backing.len() {
+            return None;
+        }
+        let mut current = element_id;
+        // First, we walk up to the root, which is an element which points to itself.
+        loop {
+            let parent = self.backing[current].get().parent;
+            if parent == current {
+                break;
+            }
+            current = parent;
+        }
+        let rep = current;
+        current = element_id;
+        // Next, we walk the same path again, pointing every element on it at the root.
+        while current != rep {
+            let mut element = self.backing[current].get();
+            let parent = element.parent;
+            element.parent = rep;
+            self.backing[current].set(element);
+            current = parent;
+        }
+        Some(rep)
+    }
+
+    /// Whether two elements belong to the same set. Elements which are not in
+    /// the structure belong to no set.
+    pub fn same_set(&self, element1: usize, element2: usize) -> bool {
+        match (self.find(element1), self.find(element2)) {
+            (Some(rep1), Some(rep2)) => rep1 == rep2,
+            _ => false,
+        }
+    }
+
+    /// Compute the number of elements in the set that this element belongs to.
+    pub fn size_of(&self, element_id: usize) -> Option<usize> {
+        let rep = self.find(element_id)?;
+        Some(self.backing[rep].get().size)
+    }
+
+    /// The number of disjoint sets currently in the structure.
+    pub fn set_count(&self) -> usize {
+        self.set_count.get()
+    }
+
+    /// Cause the union of the sets which two elements belong to, returning a
+    /// [`UnionOutcome`] describing what happened.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is not in the structure. See
+    /// [`CellUnionFind::try_union`] for a non-panicking version.
+    pub fn union(&self, element1: usize, element2: usize) -> UnionOutcome {
+        match self.try_union(element1, element2) {
+            Ok(outcome) => outcome,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Cause the union of the sets which two elements belong to, returning a
+    /// [`UnionOutcome`] describing what happened, or an error if either
+    /// element is not in the structure.
+    pub fn try_union(
+        &self,
+        element1: usize,
+        element2: usize,
+    ) -> Result<UnionOutcome, UnionFindError> {
+        let out_of_bounds = |element| UnionFindError::OutOfBounds {
+            element,
+            len: self.backing.len(),
+        };
+        let rep1 = self.find(element1).ok_or_else(|| out_of_bounds(element1))?;
+        let rep2 = self.find(element2).ok_or_else(|| out_of_bounds(element2))?;
+
+        let mut root1 = self.backing[rep1].get();
+        let mut root2 = self.backing[rep2].get();
+        if rep1 == rep2 {
+            return Ok(UnionOutcome {
+                already_joined: true,
+                rep1,
+                rep2,
+                representative: rep1,
+                size: root1.size,
+            });
+        }
+
+        let (child, parent, representative) = if root1.rank > root2.rank {
+            (&mut root2, &mut root1, rep1)
+        } else {
+            (&mut root1, &mut root2, rep2)
+        };
+        Element::link_roots(child, parent, representative);
+        let size = parent.size;
+        self.backing[rep1].set(root1);
+        self.backing[rep2].set(root2);
+        self.set_count.set(self.set_count.get() - 1);
+
+        Ok(UnionOutcome {
+            already_joined: false,
+            rep1,
+            rep2,
+            representative,
+            size,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use std::cell::Cell;
+
+    use serde::de::Deserializer;
+    use serde::ser::Serializer;
+    use serde::{Deserialize, Serialize};
+
+    use super::CellUnionFind;
+    use crate::serialize::ForestRepr;
+    use crate::Element;
+
+    impl Serialize for CellUnionFind {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            ForestRepr::new(self.backing.iter().map(Cell::get)).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for CellUnionFind {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let backing = ForestRepr::deserialize(deserializer)?.rebuild()?;
+            Ok(CellUnionFind {
+                set_count: Cell::new(Element::count_roots(&backing)),
+                backing: backing.into_iter().map(Cell::new).collect(),
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let cell = CellUnionFind::new(4);
+            cell.union(1, 3);
+            let cell: CellUnionFind =
+                serde_json::from_str(&serde_json::to_string(&cell).unwrap()).unwrap();
+            assert!(cell.same_set(1, 3));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_union_and_find() {
+        let uf = CellUnionFind::new(100);
+        let shared = &uf;
+        for i in 0..50 {
+            shared.union(i, i + 50);
+        }
+        for i in 0..49 {
+            shared.union(i, i + 1);
+        }
+        assert!(uf.same_set(0, 99));
+        assert_eq!(uf.size_of(17), Some(100));
+        assert_eq!(uf.set_count(), 1);
+        assert!(uf.union(3, 97).already_joined);
+    }
+
+    #[test]
+    fn disjoint_sets() {
+        let mut uf = CellUnionFind::new(4);
+        uf.union(0, 1);
+        let fresh = uf.fresh();
+        uf.union(fresh, 3);
+        assert!(!uf.same_set(0, fresh));
+        assert!(!uf.same_set(0, 5));
+        assert_eq!(uf.find(fresh), uf.find(3));
+        assert_eq!(uf.set_count(), 3);
+        assert!(uf.try_union(0, 5).is_err());
+    }
+}
@@ -0,0 +1,329 @@
+#[cfg(loom)]
+use loom::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+#[cfg(not(loom))]
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+
+use crate::{strategy, UnionFindError};
+
+/// The seed for the pseudorandom order in which representatives are linked.
+const LINK_SEED: u64 = 0x243f6a8885a308d3;
+
+/// A [`ConcurrentUnionFind`] maintains a fixed number of items indexed by
+/// natural numbers, each in a disjoint set, and may be shared between threads.
+///
+/// Parent pointers are atomics updated with compare-and-swap, following the
+/// randomized linking with path halving of Jayanti and Tarjan, so no operation
+/// ever takes a lock.
+pub struct ConcurrentUnionFind {
+    parents: Vec<AtomicUsize>,
+    set_count: AtomicUsize,
+}
+
+impl ConcurrentUnionFind {
+    /// Construct a new [`ConcurrentUnionFind`] with the given number of elements,
+    /// each in their own set.
+    pub fn new(size: usize) -> Self {
+        ConcurrentUnionFind {
+            parents: (0..size).map(AtomicUsize::new).collect(),
+            set_count: AtomicUsize::new(size),
+        }
+    }
+
+    /// The number of elements in the structure.
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Whether the structure has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// The number of disjoint sets currently in the structure.
+    pub fn set_count(&self) -> usize {
+        self.set_count.load(SeqCst)
+    }
+
+    /// Find the representative for the set that this element belongs to.
+    ///
+    /// Another thread may merge this set right after the representative is
+    /// found, so the result is only guaranteed to have been the representative
+    /// at some point during the call.
+    pub fn find(&self, element_id: usize) -> Option<usize> {
+        if element_id >= self.parents.len() {
+            None
+        } else {
+            Some(self.find_root(element_id))
+        }
+    }
+
+    fn find_root(&self, element_id: usize) -> usize {
+        let mut current = element_id;
+        loop {
+            let parent = self.parents[current].load(SeqCst);
+            if parent == current {
+                break current;
+            }
+            let grandparent = self.parents[parent].load(SeqCst);
+            // Path halving: if we lose this race, some other thread has already
+            // moved this element closer to the root, which is just as good.
+            let _ = self.parents[current].compare_exchange(parent, grandparent, SeqCst, SeqCst);
+            current = grandparent;
+        }
+    }
+
+    /// Whether two elements belong to the same set. Elements which are not in
+    /// the structure belong to no set.
+    pub fn same_set(&self, element1: usize, element2: usize) -> bool {
+        if element1 >= self.parents.len() || element2 >= self.parents.len() {
+            return false;
+        }
+        let mut rep1 = element1;
+        let mut rep2 = element2;
+        loop {
+            rep1 = self.find_root(rep1);
+            rep2 = self.find_root(rep2);
+            if rep1 == rep2 {
+                return true;
+            }
+            // If the first representative is still a root, then there was a
+            // moment at which both were roots of different sets.
+            if self.parents[rep1].load(SeqCst) == rep1 {
+                return false;
+            }
+        }
+    }
+
+    /// Cause the union of the sets which two elements belong to, returning
+    /// whether this call merged two distinct sets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is not in the structure. See
+    /// [`ConcurrentUnionFind::try_union`] for a non-panicking version.
+    pub fn union(&self, element1: usize, element2: usize) -> bool {
+        match self.try_union(element1, element2) {
+            Ok(merged) => merged,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Cause the union of the sets which two elements belong to, returning
+    /// whether this call merged two distinct sets, or an error if either
+    /// element is not in the structure.
+    pub fn try_union(&self, element1: usize, element2: usize) -> Result<bool, UnionFindError> {
+        for element in [element1, element2] {
+            if element >= self.parents.len() {
+                return Err(UnionFindError::OutOfBounds {
+                    element,
+                    len: self.parents.len(),
+                });
+            }
+        }
+        let mut rep1 = element1;
+        let mut rep2 = element2;
+        loop {
+            rep1 = self.find_root(rep1);
+            rep2 = self.find_root(rep2);
+            if rep1 == rep2 {
+                return Ok(false);
+            }
+            // Linking in a fixed pseudorandom order means no cycle can ever
+            // form, however the threads interleave.
+            let (child, parent) = if precedes(rep1, rep2) {
+                (rep1, rep2)
+            } else {
+                (rep2, rep1)
+            };
+            if self.parents[child]
+                .compare_exchange(child, parent, SeqCst, SeqCst)
+                .is_ok()
+            {
+                self.set_count.fetch_sub(1, SeqCst);
+                return Ok(true);
+            }
+        }
+    }
+}
+
+/// Whether the representative `a` should be linked beneath the representative `b`.
+fn precedes(a: usize, b: usize) -> bool {
+    (strategy::priority(LINK_SEED, a), a) < (strategy::priority(LINK_SEED, b), b)
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use serde::de::{self, Deserializer};
+    use serde::ser::Serializer;
+    use serde::{Deserialize, Serialize};
+
+    use super::{AtomicUsize, ConcurrentUnionFind, SeqCst};
+    use crate::Element;
+
+    #[derive(Serialize, Deserialize)]
+    struct ConcurrentRepr {
+        parents: Vec<usize>,
+    }
+
+    impl Serialize for ConcurrentUnionFind {
+        /// Serializing while other threads are linking sets may capture only some
+        /// of their unions, but always captures a valid forest.
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            ConcurrentRepr {
+                parents: self.parents.iter().map(|p| p.load(SeqCst)).collect(),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for ConcurrentUnionFind {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = ConcurrentRepr::deserialize(deserializer)?;
+            let backing = Element::forest(repr.parents, None).map_err(de::Error::custom)?;
+            Ok(ConcurrentUnionFind {
+                set_count: AtomicUsize::new(Element::count_roots(&backing)),
+                parents: backing.iter().map(|e| AtomicUsize::new(e.parent)).collect(),
+            })
+        }
+    }
+
+    #[cfg(all(test, not(loom)))]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let concurrent = ConcurrentUnionFind::new(4);
+            concurrent.union(0, 3);
+            let concurrent: ConcurrentUnionFind =
+                serde_json::from_str(&serde_json::to_string(&concurrent).unwrap()).unwrap();
+            assert!(concurrent.same_set(0, 3));
+            assert_eq!(concurrent.set_count(), 3);
+        }
+
+        #[test]
+        fn cycles_are_rejected() {
+            assert!(serde_json::from_str::<ConcurrentUnionFind>(r#"{"parents":[1,0]}"#).is_err());
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+    use crate::UnionFind;
+
+    const SIZE: usize = 1000;
+
+    #[test]
+    fn agrees_with_sequential() {
+        let edges: Vec<(usize, usize)> = (0..SIZE)
+            .map(|i| (i, (i * 7 + 3) % SIZE))
+            .filter(|&(a, b)| a % 3 == b % 3)
+            .collect();
+        let uf = Arc::new(ConcurrentUnionFind::new(SIZE));
+        let handles: Vec<_> = edges
+            .chunks(edges.len() / 4 + 1)
+            .map(|chunk| {
+                let uf = Arc::clone(&uf);
+                let chunk = chunk.to_vec();
+                thread::spawn(move || chunk.into_iter().filter(|&(a, b)| uf.union(a, b)).count())
+            })
+            .collect();
+        let merges: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+        let mut sequential = UnionFind::new(SIZE);
+        for &(a, b) in &edges {
+            sequential.union(a, b);
+        }
+        assert_eq!(uf.set_count(), sequential.set_count());
+        assert_eq!(SIZE - merges, sequential.set_count());
+        for i in 0..SIZE {
+            assert_eq!(uf.same_set(0, i), sequential.same_set(0, i));
+            assert_eq!(uf.same_set(1, i), sequential.same_set(1, i));
+        }
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let uf = ConcurrentUnionFind::new(2);
+        assert_eq!(uf.find(2), None);
+        assert!(!uf.same_set(0, 2));
+        assert_eq!(
+            uf.try_union(0, 2),
+            Err(UnionFindError::OutOfBounds { element: 2, len: 2 })
+        );
+    }
+}
+
+/// Run with `RUSTFLAGS="--cfg loom" cargo test --release --lib concurrent`.
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use loom::sync::Arc;
+    use loom::thread;
+
+    use super::*;
+    use crate::UnionFind;
+
+    const SIZE: usize = 4;
+
+    /// Perform each thread's unions concurrently, checking that the results
+    /// agree with performing all of them sequentially.
+    fn check(threads: &'static [&'static [(usize, usize)]]) {
+        // Bounding preemptions keeps the search over three threads tractable.
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound = Some(3);
+        builder.check(move || {
+            let uf = Arc::new(ConcurrentUnionFind::new(SIZE));
+            let handles: Vec<_> = threads
+                .iter()
+                .map(|&edges| {
+                    let uf = Arc::clone(&uf);
+                    thread::spawn(move || {
+                        let mut merges = 0;
+                        for &(a, b) in edges {
+                            if uf.union(a, b) {
+                                merges += 1;
+                            }
+                            assert!(uf.same_set(a, b));
+                        }
+                        merges
+                    })
+                })
+                .collect();
+            let merges: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+            let mut sequential = UnionFind::new(SIZE);
+            for edges in threads {
+                for &(a, b) in *edges {
+                    sequential.union(a, b);
+                }
+            }
+            assert_eq!(SIZE - merges, sequential.set_count());
+            assert_eq!(uf.set_count(), sequential.set_count());
+            for a in 0..SIZE {
+                for b in 0..SIZE {
+                    assert_eq!(uf.same_set(a, b), sequential.same_set(a, b));
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn racing_same_union() {
+        check(&[&[(0, 1)], &[(1, 0)]]);
+    }
+
+    #[test]
+    fn racing_overlapping_unions() {
+        check(&[&[(0, 1)], &[(1, 2)], &[(2, 0)]]);
+    }
+
+    #[test]
+    fn racing_chains() {
+        check(&[&[(0, 1), (2, 3)], &[(1, 2)]]);
+    }
+}
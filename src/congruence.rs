@@ -0,0 +1,365 @@
+//! Congruence closure over ground terms, built on [`UnionFind`].
+//!
+//! Terms are applications `f(t1, ..., tn)` of a function symbol to earlier
+//! terms, with constants being applications to no terms at all. Every term is
+//! hash-consed into an element of a [`UnionFind`], and asserting that two terms
+//! are equal also makes equal every pair of terms which are then congruent,
+//! that is, which apply the same symbol to equal arguments:
+//!
+//! ```
+//! use union_find::congruence::CongruenceClosure;
+//!
+//! let mut cc = CongruenceClosure::new();
+//! let a = cc.term("a", &[]);
+//! let b = cc.term("b", &[]);
+//! let fa = cc.term("f", &[a]);
+//! let fb = cc.term("f", &[b]);
+//! let gfa = cc.term("g", &[fa, b]);
+//! let gfb = cc.term("g", &[fb, a]);
+//!
+//! assert!(!cc.are_equal(gfa, gfb));
+//!
+//! cc.union(a, b);
+//!
+//! assert!(cc.are_equal(fa, fb));
+//! assert!(cc.are_equal(gfa, gfb));
+//! ```
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem;
+
+use crate::{UnionFind, UnionFindError};
+
+/// A set of ground terms, along with the smallest equivalence between them
+/// which contains every asserted equality and is closed under congruence.
+pub struct CongruenceClosure<F> {
+    union_find: UnionFind,
+    /// The symbol and arguments of each term.
+    terms: Vec<(F, Vec<usize>)>,
+    /// Each term, keyed by its symbol and arguments, so that terms are shared.
+    interned: HashMap<(F, Vec<usize>), usize>,
+    /// A term for each signature, which is a symbol along with the
+    /// representatives of the arguments it is applied to.
+    signatures: HashMap<(F, Vec<usize>), usize>,
+    /// The terms with an argument in each set, held only at its representative.
+    uses: Vec<Vec<usize>>,
+}
+
+impl<F: Hash + Eq + Clone> CongruenceClosure<F> {
+    /// Construct a new [`CongruenceClosure`] with no terms.
+    pub fn new() -> Self {
+        CongruenceClosure {
+            union_find: UnionFind::new(0),
+            terms: Vec::new(),
+            interned: HashMap::new(),
+            signatures: HashMap::new(),
+            uses: Vec::new(),
+        }
+    }
+
+    /// The number of distinct terms.
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    /// Whether there are no terms.
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The number of classes of equal terms.
+    pub fn class_count(&self) -> usize {
+        self.union_find.set_count()
+    }
+
+    /// The symbol and arguments of a term.
+    pub fn get(&self, term: usize) -> Option<(&F, &[usize])> {
+        let (symbol, args) = self.terms.get(term)?;
+        Some((symbol, args))
+    }
+
+    /// Find the representative term of the class that this term belongs to.
+    pub fn find(&mut self, term: usize) -> Option<usize> {
+        self.union_find.find(term)
+    }
+
+    /// Whether two terms are equal. Terms which do not exist are equal to nothing.
+    pub fn are_equal(&self, term1: usize, term2: usize) -> bool {
+        self.union_find.same_set(term1, term2)
+    }
+
+    /// Find or create the term applying a symbol to the given arguments.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any argument is not a term. See [`CongruenceClosure::try_term`]
+    /// for a non-panicking version.
+    pub fn term(&mut self, symbol: F, args: &[usize]) -> usize {
+        match self.try_term(symbol, args) {
+            Ok(term) => term,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Find or create the term applying a symbol to the given arguments, or
+    /// report an error if any argument is not a term.
+    pub fn try_term(&mut self, symbol: F, args: &[usize]) -> Result<usize, UnionFindError> {
+        for &arg in args {
+            self.union_find.try_find(arg)?;
+        }
+        let key = (symbol, args.to_vec());
+        if let Some(&term) = self.interned.get(&key) {
+            return Ok(term);
+        }
+        let term = self.union_find.fresh();
+        self.uses.push(Vec::new());
+        for &arg in args {
+            let rep = self.union_find.find(arg).unwrap();
+            self.uses[rep].push(term);
+        }
+        self.interned.insert(key.clone(), term);
+        self.terms.push(key);
+        // The new term may be congruent to one we already have.
+        if let Some(congruent) = self.insert_signature(term) {
+            self.close(vec![(term, congruent)]);
+        }
+        Ok(term)
+    }
+
+    /// Assert that two terms are equal, along with every congruence that follows,
+    /// returning whether two distinct classes were merged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either term does not exist. See [`CongruenceClosure::try_union`]
+    /// for a non-panicking version.
+    pub fn union(&mut self, term1: usize, term2: usize) -> bool {
+        match self.try_union(term1, term2) {
+            Ok(merged) => merged,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Assert that two terms are equal, along with every congruence that follows,
+    /// returning whether two distinct classes were merged, or an error if either
+    /// term does not exist.
+    pub fn try_union(&mut self, term1: usize, term2: usize) -> Result<bool, UnionFindError> {
+        let rep1 = self.union_find.try_find(term1)?;
+        let rep2 = self.union_find.try_find(term2)?;
+        if rep1 == rep2 {
+            return Ok(false);
+        }
+        self.close(vec![(rep1, rep2)]);
+        Ok(true)
+    }
+
+    /// The signature of a term, under the current representatives.
+    fn signature(&mut self, term: usize) -> (F, Vec<usize>) {
+        let (symbol, args) = &self.terms[term];
+        let (symbol, args) = (symbol.clone(), args.clone());
+        let reps = args
+            .into_iter()
+            .map(|arg| self.union_find.find(arg).unwrap())
+            .collect();
+        (symbol, reps)
+    }
+
+    /// Record the signature of a term, or return the term which already has it.
+    fn insert_signature(&mut self, term: usize) -> Option<usize> {
+        let signature = self.signature(term);
+        match self.signatures.entry(signature) {
+            Entry::Occupied(entry) if *entry.get() != term => Some(*entry.get()),
+            Entry::Occupied(_) => None,
+            Entry::Vacant(entry) => {
+                entry.insert(term);
+                None
+            }
+        }
+    }
+
+    /// Merge each pair of terms, and then every pair of terms which become
+    /// congruent as a result, until no more remain.
+    fn close(&mut self, mut pending: Vec<(usize, usize)>) {
+        while let Some((term1, term2)) = pending.pop() {
+            let rep1 = self.union_find.find(term1).unwrap();
+            let rep2 = self.union_find.find(term2).unwrap();
+            if rep1 == rep2 {
+                continue;
+            }
+            // The signatures of every term using either set are about to
+            // change, so we forget them while they can still be computed.
+            let mut uses = mem::take(&mut self.uses[rep1]);
+            uses.append(&mut self.uses[rep2]);
+            for &user in &uses {
+                let signature = self.signature(user);
+                if self.signatures.get(&signature) == Some(&user) {
+                    self.signatures.remove(&signature);
+                }
+            }
+            let representative = self.union_find.union(rep1, rep2).representative;
+            for &user in &uses {
+                if let Some(congruent) = self.insert_signature(user) {
+                    pending.push((user, congruent));
+                }
+            }
+            self.uses[representative] = uses;
+        }
+    }
+}
+
+impl<F: Hash + Eq + Clone> Default for CongruenceClosure<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use std::hash::Hash;
+
+    use serde::de::{self, Deserializer};
+    use serde::ser::Serializer;
+    use serde::{Deserialize, Serialize};
+
+    use super::CongruenceClosure;
+    use crate::UnionFind;
+
+    #[derive(Serialize, Deserialize)]
+    struct CongruenceRepr<F> {
+        terms: Vec<(F, Vec<usize>)>,
+        union_find: UnionFind,
+    }
+
+    impl<F: Serialize> Serialize for CongruenceClosure<F> {
+        /// Only the terms and the structure are serialized, as the tables used
+        /// to close over congruence can be rebuilt from them.
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            #[derive(Serialize)]
+            struct CongruenceReprRef<'a, F> {
+                terms: &'a [(F, Vec<usize>)],
+                union_find: &'a UnionFind,
+            }
+            CongruenceReprRef {
+                terms: &self.terms,
+                union_find: &self.union_find,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, F: Deserialize<'de> + Hash + Eq + Clone> Deserialize<'de> for CongruenceClosure<F> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = CongruenceRepr::<F>::deserialize(deserializer)?;
+            if repr.terms.len() != repr.union_find.backing.len() {
+                return Err(de::Error::custom(format!(
+                    "there are {} terms for {} elements",
+                    repr.terms.len(),
+                    repr.union_find.backing.len()
+                )));
+            }
+            // We add the terms again in order, so every argument must be an
+            // earlier term, and then join each term to its representative.
+            let mut closure = CongruenceClosure::new();
+            for (id, (symbol, args)) in repr.terms.into_iter().enumerate() {
+                if args.iter().any(|&arg| arg >= id) {
+                    return Err(de::Error::custom(format!(
+                        "term {id} has an argument which is not an earlier term"
+                    )));
+                }
+                if closure.term(symbol, &args) != id {
+                    return Err(de::Error::custom(format!("term {id} is a duplicate")));
+                }
+            }
+            for id in 0..closure.len() {
+                closure.union(id, repr.union_find.find_immutable(id).unwrap());
+            }
+            Ok(closure)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let mut congruence = CongruenceClosure::new();
+            let a = congruence.term("a".to_string(), &[]);
+            let b = congruence.term("b".to_string(), &[]);
+            let fa = congruence.term("f".to_string(), &[a]);
+            let fb = congruence.term("f".to_string(), &[b]);
+            congruence.union(a, b);
+            let congruence: CongruenceClosure<String> =
+                serde_json::from_str(&serde_json::to_string(&congruence).unwrap()).unwrap();
+            assert!(congruence.are_equal(fa, fb));
+            assert_eq!(congruence.class_count(), 2);
+        }
+
+        #[test]
+        fn later_arguments_are_rejected() {
+            assert!(serde_json::from_str::<CongruenceClosure<char>>(
+                r#"{"terms":[["f",[0]]],"union_find":{"parents":[0],"ranks":[0]}}"#
+            )
+            .is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terms_are_hash_consed() {
+        let mut cc = CongruenceClosure::new();
+        let a = cc.term('a', &[]);
+        let fa = cc.term('f', &[a]);
+        assert_eq!(cc.term('a', &[]), a);
+        assert_eq!(cc.term('f', &[a]), fa);
+        assert_ne!(cc.term('g', &[a]), fa);
+        assert_eq!(cc.len(), 3);
+        assert_eq!(cc.get(fa), Some((&'f', &[a][..])));
+        assert!(cc.try_term('f', &[7]).is_err());
+        assert_eq!(cc.len(), 3);
+    }
+
+    #[test]
+    fn congruence_propagates() {
+        // From f(f(f(a))) = a and f(f(f(f(f(a))))) = a it follows that f(a) = a.
+        let mut cc = CongruenceClosure::new();
+        let mut powers = vec![cc.term("a", &[])];
+        for i in 0..5 {
+            powers.push(cc.term("f", &[powers[i]]));
+        }
+        assert_eq!(cc.class_count(), 6);
+        assert!(cc.union(powers[3], powers[0]));
+        assert!(!cc.are_equal(powers[1], powers[0]));
+        assert!(cc.are_equal(powers[4], powers[1]));
+        assert!(cc.union(powers[5], powers[0]));
+        assert!(cc.are_equal(powers[1], powers[0]));
+        assert_eq!(cc.class_count(), 1);
+        assert!(!cc.union(powers[2], powers[4]));
+    }
+
+    #[test]
+    fn new_terms_join_congruent_classes() {
+        let mut cc = CongruenceClosure::new();
+        let a = cc.term("a", &[]);
+        let b = cc.term("b", &[]);
+        let c = cc.term("c", &[]);
+        cc.union(a, b);
+        let hab = cc.term("h", &[a, b]);
+        let hba = cc.term("h", &[b, a]);
+        assert_ne!(hab, hba);
+        assert!(cc.are_equal(hab, hba));
+        let hac = cc.term("h", &[a, c]);
+        assert!(!cc.are_equal(hab, hac));
+        cc.union(c, b);
+        assert!(cc.are_equal(hab, hac));
+        assert_eq!(cc.class_count(), 2);
+        assert!(!cc.are_equal(a, 9));
+        assert!(cc.try_union(a, 9).is_err());
+    }
+}
@@ -0,0 +1,706 @@
+//! E-graphs, which compactly represent many equivalent terms at once, built on
+//! [`UnionFind`].
+//!
+//! An e-graph is made of e-nodes, each applying a symbol to e-classes, and
+//! e-classes, each a set of e-nodes known to be equal and identified by its
+//! representative in a [`UnionFind`]. Unlike [`congruence`](crate::congruence),
+//! merging e-classes does not immediately restore congruence: that is deferred
+//! to [`EGraph::rebuild`], so that the work of many merges can be shared.
+//!
+//! ```
+//! use union_find::egraph::{EGraph, ENode};
+//!
+//! let mut egraph = EGraph::new(());
+//! let x = egraph.add(ENode::leaf("x"));
+//! let two = egraph.add(ENode::leaf("2"));
+//! let times = egraph.add(ENode::new("*", vec![x, two]));
+//! let one = egraph.add(ENode::leaf("1"));
+//! let shift = egraph.add(ENode::new("<<", vec![x, one]));
+//!
+//! egraph.union(times, shift);
+//! egraph.rebuild();
+//!
+//! // Count each symbol as costing one, and prefer the shorter name.
+//! let (cost, term) = egraph
+//!     .extract(times, |node, children: &[usize]| {
+//!         node.symbol.len() + children.iter().sum::<usize>()
+//!     })
+//!     .unwrap();
+//! assert_eq!(cost, 3);
+//! assert_eq!(term.last().unwrap().symbol, "*");
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::mem;
+
+use crate::UnionFind;
+
+/// An e-node, applying a symbol to some e-classes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ENode<F> {
+    /// The symbol being applied.
+    pub symbol: F,
+    /// The e-classes it is applied to.
+    pub children: Vec<usize>,
+}
+
+impl<F> ENode<F> {
+    /// Construct an e-node applying a symbol to the given e-classes.
+    pub fn new(symbol: F, children: Vec<usize>) -> Self {
+        ENode { symbol, children }
+    }
+
+    /// Construct an e-node with no children.
+    pub fn leaf(symbol: F) -> Self {
+        ENode::new(symbol, Vec::new())
+    }
+}
+
+/// Some data computed for every e-class from its e-nodes, such as the constant
+/// it is known to equal or the type of its terms.
+pub trait Analysis<F> {
+    /// The data associated with each e-class.
+    type Data: Clone + PartialEq;
+
+    /// Compute the data of an e-node from the data of its children.
+    fn make(&mut self, node: &ENode<F>, children: &[&Self::Data]) -> Self::Data;
+
+    /// Merge the data of two e-classes which have been found to be equal.
+    ///
+    /// For the result not to depend on the order of merges, this should be
+    /// associative and commutative, as in a semilattice.
+    fn merge(&mut self, first: Self::Data, second: Self::Data) -> Self::Data;
+}
+
+/// The analysis which computes nothing.
+impl<F> Analysis<F> for () {
+    type Data = ();
+
+    fn make(&mut self, _: &ENode<F>, _: &[&()]) {}
+
+    fn merge(&mut self, _: (), _: ()) {}
+}
+
+/// An e-class, which is a set of equal e-nodes along with their merged data.
+pub struct EClass<F, D> {
+    nodes: Vec<ENode<F>>,
+    data: D,
+    /// The e-nodes with this e-class as a child, along with their e-classes.
+    parents: Vec<(ENode<F>, usize)>,
+}
+
+impl<F, D> EClass<F, D> {
+    /// The e-nodes in this e-class. These are only free of duplicates, and
+    /// only refer to representatives, when the e-graph is clean.
+    pub fn nodes(&self) -> &[ENode<F>] {
+        &self.nodes
+    }
+
+    /// The data computed for this e-class.
+    pub fn data(&self) -> &D {
+        &self.data
+    }
+}
+
+/// An e-graph over e-nodes with symbols of type `F`, keeping some [`Analysis`]
+/// for every e-class.
+pub struct EGraph<F, A: Analysis<F>> {
+    union_find: UnionFind,
+    analysis: A,
+    /// The e-class of every e-node, keyed by its children's representatives at
+    /// the time it was last visited.
+    memo: HashMap<ENode<F>, usize>,
+    /// The e-classes, held only at their representatives.
+    classes: Vec<Option<EClass<F, A::Data>>>,
+    /// E-nodes whose children have been merged, and so need to be looked up again.
+    pending: Vec<(ENode<F>, usize)>,
+    /// E-nodes whose children's data has changed, and so need to be analysed again.
+    analysis_pending: Vec<(ENode<F>, usize)>,
+}
+
+impl<F: Hash + Eq + Clone, A: Analysis<F>> EGraph<F, A> {
+    /// Construct a new, empty [`EGraph`] keeping the given analysis.
+    pub fn new(analysis: A) -> Self {
+        EGraph {
+            union_find: UnionFind::new(0),
+            analysis,
+            memo: HashMap::new(),
+            classes: Vec::new(),
+            pending: Vec::new(),
+            analysis_pending: Vec::new(),
+        }
+    }
+
+    /// The analysis kept by this e-graph.
+    pub fn analysis(&self) -> &A {
+        &self.analysis
+    }
+
+    /// The number of e-classes.
+    pub fn class_count(&self) -> usize {
+        self.union_find.set_count()
+    }
+
+    /// Whether every merge has been followed by a [`EGraph::rebuild`], so that
+    /// congruence holds and every analysis is up to date.
+    pub fn is_clean(&self) -> bool {
+        self.pending.is_empty() && self.analysis_pending.is_empty()
+    }
+
+    /// Find the representative of the e-class which this e-class has been merged into.
+    pub fn find(&mut self, class: usize) -> Option<usize> {
+        self.union_find.find(class)
+    }
+
+    /// The e-class which this e-class has been merged into.
+    pub fn class(&self, class: usize) -> Option<&EClass<F, A::Data>> {
+        let rep = self.union_find.find_immutable(class)?;
+        self.classes[rep].as_ref()
+    }
+
+    /// Iterate over every e-class, along with its representative.
+    pub fn classes(&self) -> impl Iterator<Item = (usize, &EClass<F, A::Data>)> {
+        self.classes
+            .iter()
+            .enumerate()
+            .filter_map(|(rep, class)| Some((rep, class.as_ref()?)))
+    }
+
+    /// Rewrite the children of an e-node to be representatives.
+    fn canonicalize(&mut self, mut node: ENode<F>) -> ENode<F> {
+        for child in &mut node.children {
+            *child = self.union_find.find(*child).unwrap();
+        }
+        node
+    }
+
+    /// Find the e-class containing an e-node, if any.
+    pub fn lookup(&mut self, node: ENode<F>) -> Option<usize> {
+        if node
+            .children
+            .iter()
+            .any(|&child| child >= self.classes.len())
+        {
+            return None;
+        }
+        let node = self.canonicalize(node);
+        let class = *self.memo.get(&node)?;
+        self.find(class)
+    }
+
+    /// Compute the data of a canonical e-node.
+    fn make(&mut self, node: &ENode<F>) -> A::Data {
+        let children: Vec<_> = node
+            .children
+            .iter()
+            .map(|&child| &self.classes[child].as_ref().unwrap().data)
+            .collect();
+        self.analysis.make(node, &children)
+    }
+
+    /// Add an e-node to the e-graph, returning its e-class. If the e-node is
+    /// already present, the existing e-class is returned instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any child is not an e-class.
+    pub fn add(&mut self, node: ENode<F>) -> usize {
+        for &child in &node.children {
+            assert!(
+                child < self.classes.len(),
+                "child {child} is not an e-class of the {} in the e-graph",
+                self.classes.len()
+            );
+        }
+        let node = self.canonicalize(node);
+        if let Some(&class) = self.memo.get(&node) {
+            return self.union_find.find(class).unwrap();
+        }
+        let class = self.union_find.fresh();
+        let data = self.make(&node);
+        for &child in &node.children {
+            let parents = &mut self.classes[child].as_mut().unwrap().parents;
+            parents.push((node.clone(), class));
+        }
+        self.classes.push(Some(EClass {
+            nodes: vec![node.clone()],
+            data,
+            parents: Vec::new(),
+        }));
+        self.memo.insert(node, class);
+        class
+    }
+
+    /// Merge two e-classes, returning whether they were distinct. Congruence
+    /// is only restored, and analyses only brought up to date, by the next
+    /// call to [`EGraph::rebuild`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if either e-class is not in the e-graph.
+    pub fn union(&mut self, class1: usize, class2: usize) -> bool {
+        let outcome = self.union_find.union(class1, class2);
+        if outcome.already_joined {
+            return false;
+        }
+        let (representative, absorbed) = if outcome.representative == outcome.rep1 {
+            (outcome.rep1, outcome.rep2)
+        } else {
+            (outcome.rep2, outcome.rep1)
+        };
+        let first = self.classes[outcome.rep1].as_ref().unwrap().data.clone();
+        let second = self.classes[outcome.rep2].as_ref().unwrap().data.clone();
+        let data = self.analysis.merge(first.clone(), second.clone());
+
+        let absorbed = self.classes[absorbed].take().unwrap();
+        // Only the parents of the absorbed e-class refer to a representative
+        // which is no longer one.
+        self.pending.extend(absorbed.parents.iter().cloned());
+        let class = self.classes[representative].as_mut().unwrap();
+        class.nodes.extend(absorbed.nodes);
+        class.parents.extend(absorbed.parents);
+        if data != first || data != second {
+            self.analysis_pending.extend(class.parents.iter().cloned());
+        }
+        class.data = data;
+        true
+    }
+
+    /// Restore congruence, merging every pair of e-classes which contain e-nodes
+    /// applying the same symbol to the same e-classes, and bring every analysis
+    /// up to date. Returns the number of merges this caused.
+    pub fn rebuild(&mut self) -> usize {
+        let mut merges = 0;
+        while !self.is_clean() {
+            while let Some((node, class)) = self.pending.pop() {
+                self.memo.remove(&node);
+                let node = self.canonicalize(node);
+                let class = self.union_find.find(class).unwrap();
+                if let Some(existing) = self.memo.insert(node, class) {
+                    if self.union(existing, class) {
+                        merges += 1;
+                    }
+                }
+            }
+            while let Some((node, class)) = self.analysis_pending.pop() {
+                let node = self.canonicalize(node);
+                let class = self.union_find.find(class).unwrap();
+                let made = self.make(&node);
+                let current = self.classes[class].as_ref().unwrap().data.clone();
+                let data = self.analysis.merge(current.clone(), made);
+                if data != current {
+                    let class = self.classes[class].as_mut().unwrap();
+                    self.analysis_pending.extend(class.parents.iter().cloned());
+                    class.data = data;
+                }
+            }
+        }
+        // Finally, every e-node is made to refer to representatives, which
+        // leaves some of them duplicated.
+        for rep in 0..self.classes.len() {
+            let Some(class) = self.classes[rep].as_mut() else {
+                continue;
+            };
+            let nodes = mem::take(&mut class.nodes);
+            let mut seen = HashSet::with_capacity(nodes.len());
+            let nodes: Vec<_> = nodes
+                .into_iter()
+                .map(|node| self.canonicalize(node))
+                .filter(|node| seen.insert(node.clone()))
+                .collect();
+            self.classes[rep].as_mut().unwrap().nodes = nodes;
+        }
+        merges
+    }
+
+    /// Extract a term of least cost from an e-class, rebuilding the e-graph first
+    /// if it is not clean.
+    ///
+    /// The cost of an e-node is computed from the costs of its children, and
+    /// must be at least as large as each of them. The term is returned as a
+    /// list of e-nodes in which each child is the index of an earlier e-node,
+    /// with the root last. Returns [`None`] if the e-class is not in the e-graph.
+    pub fn extract<C: Ord + Clone>(
+        &mut self,
+        class: usize,
+        mut cost: impl FnMut(&ENode<F>, &[C]) -> C,
+    ) -> Option<(C, Vec<ENode<F>>)> {
+        let root = self.find(class)?;
+        self.rebuild();
+        let root = self.find(root).unwrap();
+
+        // We find the cheapest e-node of every e-class by improving our
+        // choices until they no longer change.
+        let mut best: Vec<Option<(C, usize)>> = vec![None; self.classes.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (rep, class) in self.classes() {
+                for (index, node) in class.nodes.iter().enumerate() {
+                    let children: Option<Vec<C>> = node
+                        .children
+                        .iter()
+                        .map(|&child| Some(best[child].as_ref()?.0.clone()))
+                        .collect();
+                    let Some(children) = children else {
+                        continue;
+                    };
+                    let node_cost = cost(node, &children);
+                    if best[rep]
+                        .as_ref()
+                        .is_none_or(|(current, _)| node_cost < *current)
+                    {
+                        best[rep] = Some((node_cost, index));
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        // Every e-class was created from an e-node whose children already had
+        // terms, so every e-class has a term.
+        let (root_cost, _) = best[root].clone().unwrap();
+        let mut term = Vec::new();
+        let mut indices = HashMap::new();
+        let mut stack = vec![(root, false)];
+        while let Some((rep, ready)) = stack.pop() {
+            if indices.contains_key(&rep) {
+                continue;
+            }
+            let (_, index) = best[rep].as_ref().unwrap();
+            let node = &self.classes[rep].as_ref().unwrap().nodes[*index];
+            if ready {
+                let children = node.children.iter().map(|child| indices[child]).collect();
+                indices.insert(rep, term.len());
+                term.push(ENode::new(node.symbol.clone(), children));
+            } else {
+                stack.push((rep, true));
+                stack.extend(node.children.iter().map(|&child| (child, false)));
+            }
+        }
+        Some((root_cost, term))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use std::collections::HashMap;
+    use std::hash::Hash;
+
+    use serde::de::{self, Deserializer};
+    use serde::ser::Serializer;
+    use serde::{Deserialize, Serialize};
+
+    use super::{Analysis, EClass, EGraph, ENode};
+    use crate::UnionFind;
+
+    #[derive(Serialize, Deserialize)]
+    struct EGraphRepr<F> {
+        union_find: UnionFind,
+        nodes: Vec<(ENode<F>, usize)>,
+    }
+
+    impl<F: Serialize, A: Analysis<F>> Serialize for EGraph<F, A> {
+        /// Only the structure and the e-nodes of every e-class are serialized, as
+        /// the data of every e-class can be computed again from them.
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            #[derive(Serialize)]
+            struct EGraphReprRef<'a, F> {
+                union_find: &'a UnionFind,
+                nodes: Vec<(&'a ENode<F>, usize)>,
+            }
+            let nodes = self
+                .classes
+                .iter()
+                .enumerate()
+                .filter_map(|(rep, class)| Some((rep, class.as_ref()?)))
+                .flat_map(|(rep, class)| class.nodes.iter().map(move |node| (node, rep)))
+                .collect();
+            EGraphReprRef {
+                union_find: &self.union_find,
+                nodes,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, F, A> Deserialize<'de> for EGraph<F, A>
+    where
+        F: Deserialize<'de> + Hash + Eq + Clone,
+        A: Analysis<F> + Default,
+    {
+        /// The [`Analysis`] is constructed with its [`Default`] implementation,
+        /// and the deserialized e-graph is rebuilt.
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = EGraphRepr::<F>::deserialize(deserializer)?;
+            let mut union_find = repr.union_find;
+            let len = union_find.backing.len();
+            let mut nodes = Vec::with_capacity(repr.nodes.len());
+            for (node, class) in repr.nodes {
+                if class >= len || node.children.iter().any(|&child| child >= len) {
+                    return Err(de::Error::custom(format!(
+                        "an e-node of e-class {class} refers to an e-class which does not exist"
+                    )));
+                }
+                let node = ENode {
+                    children: node
+                        .children
+                        .iter()
+                        .map(|&child| union_find.find(child).unwrap())
+                        .collect(),
+                    symbol: node.symbol,
+                };
+                nodes.push((node, union_find.find(class).unwrap()));
+            }
+
+            // Every e-class must contain some finite term, so we give each e-class
+            // the data of the first of its e-nodes whose children all have data.
+            let mut analysis = A::default();
+            let mut data: Vec<Option<A::Data>> = vec![None; len];
+            let mut waiting: Vec<usize> =
+                nodes.iter().map(|(node, _)| node.children.len()).collect();
+            let mut users = vec![Vec::new(); len];
+            for (i, (node, _)) in nodes.iter().enumerate() {
+                for &child in &node.children {
+                    users[child].push(i);
+                }
+            }
+            let mut ready: Vec<usize> = (0..nodes.len()).filter(|&i| waiting[i] == 0).collect();
+            while let Some(i) = ready.pop() {
+                let (node, class) = &nodes[i];
+                if data[*class].is_some() {
+                    continue;
+                }
+                let children: Vec<_> = node
+                    .children
+                    .iter()
+                    .map(|&child| data[child].as_ref().unwrap())
+                    .collect();
+                data[*class] = Some(analysis.make(node, &children));
+                for &user in &users[*class] {
+                    waiting[user] -= 1;
+                    if waiting[user] == 0 {
+                        ready.push(user);
+                    }
+                }
+            }
+            for (rep, data) in data.iter().enumerate() {
+                if union_find.backing[rep].parent == rep && data.is_none() {
+                    return Err(de::Error::custom(format!(
+                        "e-class {rep} contains no finite term"
+                    )));
+                }
+            }
+
+            // Every e-node is then analysed again, which merges the data of every
+            // e-node into its e-class.
+            let mut classes: Vec<_> = data
+                .into_iter()
+                .map(|data| {
+                    Some(EClass {
+                        nodes: Vec::new(),
+                        data: data?,
+                        parents: Vec::new(),
+                    })
+                })
+                .collect();
+            for (node, class) in &nodes {
+                for &child in &node.children {
+                    let parents = &mut classes[child].as_mut().unwrap().parents;
+                    parents.push((node.clone(), *class));
+                }
+                classes[*class].as_mut().unwrap().nodes.push(node.clone());
+            }
+            let mut egraph = EGraph {
+                union_find,
+                analysis,
+                memo: HashMap::new(),
+                classes,
+                pending: Vec::new(),
+                analysis_pending: nodes.clone(),
+            };
+            // E-classes sharing an e-node are merged, and rebuilding takes care
+            // of whatever congruences follow.
+            for (node, class) in nodes {
+                if let Some(existing) = egraph.memo.insert(node, class) {
+                    egraph.union(existing, class);
+                }
+            }
+            egraph.rebuild();
+            Ok(egraph)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let mut egraph = EGraph::new(());
+            let x = egraph.add(ENode::leaf("x".to_string()));
+            let y = egraph.add(ENode::leaf("y".to_string()));
+            let fx = egraph.add(ENode::new("f".to_string(), vec![x]));
+            let fy = egraph.add(ENode::new("f".to_string(), vec![y]));
+            egraph.union(x, y);
+            let mut egraph: EGraph<String, ()> =
+                serde_json::from_str(&serde_json::to_string(&egraph).unwrap()).unwrap();
+            assert!(egraph.is_clean());
+            assert_eq!(egraph.find(fx), egraph.find(fy));
+            assert_eq!(egraph.class_count(), 2);
+            assert_eq!(
+                egraph.lookup(ENode::new("f".to_string(), vec![y])),
+                egraph.find(fx)
+            );
+        }
+
+        #[test]
+        fn corrupted_input_is_rejected() {
+            let corrupted = |json: &str| serde_json::from_str::<EGraph<char, ()>>(json).is_err();
+            // A child which is not an e-class.
+            assert!(corrupted(
+                r#"{"union_find":{"parents":[0],"ranks":[0]},"nodes":[[{"symbol":"f","children":[1]},0]]}"#
+            ));
+            // An e-class with no e-nodes.
+            assert!(corrupted(
+                r#"{"union_find":{"parents":[0,1],"ranks":[0,0]},"nodes":[[{"symbol":"a","children":[]},0]]}"#
+            ));
+            // An e-class with no finite term.
+            assert!(corrupted(
+                r#"{"union_find":{"parents":[0],"ranks":[0]},"nodes":[[{"symbol":"f","children":[0]},0]]}"#
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Folds constants in sums and products.
+    struct ConstantFolding;
+
+    impl Analysis<&'static str> for ConstantFolding {
+        type Data = Option<i64>;
+
+        fn make(&mut self, node: &ENode<&'static str>, children: &[&Option<i64>]) -> Option<i64> {
+            match (node.symbol, children) {
+                ("+", [Some(a), Some(b)]) => Some(a + b),
+                ("*", [Some(a), Some(b)]) => Some(a * b),
+                (symbol, []) => symbol.parse().ok(),
+                _ => None,
+            }
+        }
+
+        fn merge(&mut self, first: Option<i64>, second: Option<i64>) -> Option<i64> {
+            first.or(second)
+        }
+    }
+
+    fn size(_: &ENode<&'static str>, children: &[usize]) -> usize {
+        1 + children.iter().sum::<usize>()
+    }
+
+    #[test]
+    fn nodes_are_shared() {
+        let mut egraph = EGraph::new(());
+        let x = egraph.add(ENode::leaf("x"));
+        let fx = egraph.add(ENode::new("f", vec![x]));
+        assert_eq!(egraph.add(ENode::leaf("x")), x);
+        assert_eq!(egraph.add(ENode::new("f", vec![x])), fx);
+        assert_eq!(egraph.lookup(ENode::new("f", vec![x])), Some(fx));
+        assert_eq!(egraph.lookup(ENode::new("f", vec![fx])), None);
+        assert_eq!(egraph.lookup(ENode::new("f", vec![9])), None);
+        assert_eq!(egraph.class_count(), 2);
+        assert!(egraph.is_clean());
+    }
+
+    #[test]
+    fn rebuilding_restores_congruence() {
+        let mut egraph = EGraph::new(());
+        let a = egraph.add(ENode::leaf("a"));
+        let b = egraph.add(ENode::leaf("b"));
+        let fa = egraph.add(ENode::new("f", vec![a]));
+        let fb = egraph.add(ENode::new("f", vec![b]));
+        let gfa = egraph.add(ENode::new("g", vec![fa]));
+        let gfb = egraph.add(ENode::new("g", vec![fb]));
+
+        assert!(egraph.union(a, b));
+        assert!(!egraph.union(b, a));
+        assert!(!egraph.is_clean());
+        assert_ne!(egraph.find(fa), egraph.find(fb));
+
+        assert_eq!(egraph.rebuild(), 2);
+        assert!(egraph.is_clean());
+        assert_eq!(egraph.find(fa), egraph.find(fb));
+        assert_eq!(egraph.find(gfa), egraph.find(gfb));
+        assert_eq!(egraph.class_count(), 3);
+        assert_eq!(egraph.class(fa).unwrap().nodes().len(), 1);
+        assert_eq!(egraph.classes().count(), 3);
+        // Only the canonical form of each e-node is remembered.
+        let nodes: usize = egraph.classes().map(|(_, class)| class.nodes().len()).sum();
+        assert_eq!(egraph.memo.len(), nodes);
+    }
+
+    #[test]
+    fn analysis_is_merged_and_propagated() {
+        let mut egraph = EGraph::new(ConstantFolding);
+        let x = egraph.add(ENode::leaf("x"));
+        let one = egraph.add(ENode::leaf("1"));
+        let two = egraph.add(ENode::leaf("2"));
+        let sum = egraph.add(ENode::new("+", vec![x, one]));
+        let product = egraph.add(ENode::new("*", vec![sum, two]));
+        assert_eq!(egraph.class(product).unwrap().data(), &None);
+
+        // Learning that x = 2 makes the sum and then the product constant.
+        egraph.union(x, two);
+        egraph.rebuild();
+        assert_eq!(egraph.class(x).unwrap().data(), &Some(2));
+        assert_eq!(egraph.class(sum).unwrap().data(), &Some(3));
+        assert_eq!(egraph.class(product).unwrap().data(), &Some(6));
+    }
+
+    #[test]
+    fn extraction_finds_the_cheapest_term() {
+        let mut egraph = EGraph::new(());
+        let a = egraph.add(ENode::leaf("a"));
+        let zero = egraph.add(ENode::leaf("0"));
+        let sum = egraph.add(ENode::new("+", vec![a, zero]));
+        let doubled = egraph.add(ENode::new("*", vec![sum, sum]));
+        assert_eq!(
+            egraph.extract(doubled, size),
+            Some((
+                7,
+                vec![
+                    ENode::leaf("0"),
+                    ENode::leaf("a"),
+                    ENode::new("+", vec![1, 0]),
+                    ENode::new("*", vec![2, 2]),
+                ]
+            ))
+        );
+
+        // Once a + 0 = a, the sum is cheaper, even though it is now cyclic.
+        egraph.union(sum, a);
+        let (cost, term) = egraph.extract(doubled, size).unwrap();
+        assert_eq!(cost, 3);
+        assert_eq!(term, vec![ENode::leaf("a"), ENode::new("*", vec![0, 0])]);
+        assert_eq!(egraph.extract(99, size), None);
+    }
+
+    #[test]
+    fn extraction_ignores_cycles() {
+        let mut egraph = EGraph::new(());
+        let x = egraph.add(ENode::leaf("x"));
+        let fx = egraph.add(ENode::new("f", vec![x]));
+        let gfx = egraph.add(ENode::new("g", vec![fx]));
+        egraph.union(x, gfx);
+        assert_eq!(egraph.extract(gfx, size), Some((1, vec![ENode::leaf("x")])));
+        assert_eq!(
+            egraph.extract(fx, size),
+            Some((2, vec![ENode::leaf("x"), ENode::new("f", vec![0])]))
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use std::error::Error;
+use std::fmt;
+
+/// The ways in which an operation on one of the structures in this crate can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum UnionFindError {
+    /// The element was never added to a structure holding `len` elements.
+    OutOfBounds { element: usize, len: usize },
+    /// The sets of the two elements cannot be merged without contradicting
+    /// what the structure already knows about them.
+    IncompatibleMerge { element1: usize, element2: usize },
+    /// While rebuilding a structure, the element's parent was out of bounds.
+    InvalidParent { element: usize, parent: usize },
+    /// While rebuilding a structure, the element's parent pointers led back to it.
+    Cycle { element: usize },
+    /// While rebuilding a structure, the element's rank was missing, was not
+    /// less than the number of elements, or was not less than the rank of its
+    /// parent.
+    InvalidRank { element: usize },
+}
+
+impl fmt::Display for UnionFindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnionFindError::OutOfBounds { element, len } => write!(
+                f,
+                "element {element} is out of bounds for a structure of {len} elements"
+            ),
+            UnionFindError::IncompatibleMerge { element1, element2 } => write!(
+                f,
+                "the sets of elements {element1} and {element2} cannot be merged"
+            ),
+            UnionFindError::InvalidParent { element, parent } => write!(
+                f,
+                "element {element} has parent {parent}, which is out of bounds"
+            ),
+            UnionFindError::Cycle { element } => {
+                write!(f, "the parent pointers of element {element} form a cycle")
+            }
+            UnionFindError::InvalidRank { element } => write!(
+                f,
+                "element {element} has a missing or out of range rank, or does not rank below its parent"
+            ),
+        }
+    }
+}
+
+impl Error for UnionFindError {}
@@ -0,0 +1,337 @@
+use std::mem;
+
+use crate::{UnionFind, UnionFindError, UnionOutcome};
+
+/// An [`ExplainingUnionFind`] is a [`UnionFind`] which records a justification
+/// for every union, so that it can explain why two elements are in the same set.
+///
+/// Alongside the compressed structure used by [`ExplainingUnionFind::find`], it
+/// keeps a proof forest, as in congruence closure: every union which merged two
+/// distinct sets adds an edge between the two elements it was given, so that
+/// the edges within each set form a tree, and the path between two elements in
+/// that tree is made only of unions that were asked for.
+///
+/// ```
+/// use union_find::ExplainingUnionFind;
+///
+/// let mut uf = ExplainingUnionFind::new(4);
+///
+/// uf.union(0, 1, "axiom");
+/// uf.union(2, 1, "lemma");
+/// uf.union(0, 2, "redundant");
+///
+/// assert_eq!(uf.explain(0, 2), Some(vec![(0, 1, "axiom"), (1, 2, "lemma")]));
+/// assert_eq!(uf.explain(0, 3), None);
+/// ```
+pub struct ExplainingUnionFind<J> {
+    inner: UnionFind,
+    /// The parent of each element in the proof forest, along with the
+    /// justification of the union which joined the two.
+    proofs: Vec<Option<(usize, J)>>,
+}
+
+impl<J> ExplainingUnionFind<J> {
+    /// Construct a new [`ExplainingUnionFind`] with the given number of elements,
+    /// each in their own set.
+    pub fn new(size: usize) -> Self {
+        ExplainingUnionFind {
+            inner: UnionFind::new(size),
+            proofs: (0..size).map(|_| None).collect(),
+        }
+    }
+
+    /// Add a fresh element into the union find structure.
+    pub fn fresh(&mut self) -> usize {
+        self.proofs.push(None);
+        self.inner.fresh()
+    }
+
+    /// Find the representative for the set that this element belongs to.
+    pub fn find(&mut self, element_id: usize) -> Option<usize> {
+        self.inner.find(element_id)
+    }
+
+    /// Whether two elements belong to the same set. Elements which are not in
+    /// the structure belong to no set.
+    pub fn same_set(&self, element1: usize, element2: usize) -> bool {
+        self.inner.same_set(element1, element2)
+    }
+
+    /// Compute the number of elements in the set that this element belongs to.
+    pub fn size_of(&mut self, element_id: usize) -> Option<usize> {
+        self.inner.size_of(element_id)
+    }
+
+    /// The number of disjoint sets currently in the structure.
+    pub fn set_count(&self) -> usize {
+        self.inner.set_count()
+    }
+
+    /// Cause the union of the sets which two elements belong to, recording the
+    /// justification if two distinct sets were merged, and returning a
+    /// [`UnionOutcome`] describing what happened.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is not in the structure. See
+    /// [`ExplainingUnionFind::try_union`] for a non-panicking version.
+    pub fn union(&mut self, element1: usize, element2: usize, justification: J) -> UnionOutcome {
+        match self.try_union(element1, element2, justification) {
+            Ok(outcome) => outcome,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Cause the union of the sets which two elements belong to, recording the
+    /// justification if two distinct sets were merged, and returning a
+    /// [`UnionOutcome`] describing what happened, or an error if either element
+    /// is not in the structure.
+    pub fn try_union(
+        &mut self,
+        element1: usize,
+        element2: usize,
+        justification: J,
+    ) -> Result<UnionOutcome, UnionFindError> {
+        let size1 = self.inner.size_of(element1);
+        let size2 = self.inner.size_of(element2);
+        let outcome = self.inner.try_union(element1, element2)?;
+        if !outcome.already_joined {
+            // We hang the smaller proof tree off the larger one, which means
+            // rerooting the smaller tree at the element we were given.
+            let (child, parent) = if size1 < size2 {
+                (element1, element2)
+            } else {
+                (element2, element1)
+            };
+            self.reroot(child);
+            self.proofs[child] = Some((parent, justification));
+        }
+        Ok(outcome)
+    }
+
+    /// Make this element the root of its proof tree, by reversing every edge on
+    /// the path from it to the current root.
+    fn reroot(&mut self, element_id: usize) {
+        let mut current = element_id;
+        let mut reversed = None;
+        while let Some((parent, justification)) = mem::replace(&mut self.proofs[current], reversed)
+        {
+            reversed = Some((current, justification));
+            current = parent;
+        }
+    }
+
+    /// The number of edges between this element and the root of its proof tree.
+    fn depth(&self, element_id: usize) -> usize {
+        let mut depth = 0;
+        let mut current = element_id;
+        while let Some((parent, _)) = self.proofs[current] {
+            depth += 1;
+            current = parent;
+        }
+        depth
+    }
+}
+
+impl<J: Clone> ExplainingUnionFind<J> {
+    /// Explain why two elements are in the same set, as a path of unions which
+    /// leads from the first element to the second. Each step is given as the
+    /// two elements of a union, in the order the path visits them, along with
+    /// its justification.
+    ///
+    /// Returns [`None`] if the elements are not in the same set.
+    pub fn explain(&self, element1: usize, element2: usize) -> Option<Vec<(usize, usize, J)>> {
+        if !self.same_set(element1, element2) {
+            return None;
+        }
+        let step = |element_id: usize| self.proofs[element_id].clone().unwrap();
+        let (mut current1, mut current2) = (element1, element2);
+        let (mut depth1, mut depth2) = (self.depth(element1), self.depth(element2));
+        let mut from_first = Vec::new();
+        let mut from_second = Vec::new();
+        // We climb from both elements to their nearest common ancestor, with the
+        // second path collected backwards.
+        while current1 != current2 {
+            if depth1 >= depth2 {
+                let (parent, justification) = step(current1);
+                from_first.push((current1, parent, justification));
+                current1 = parent;
+                depth1 -= 1;
+            } else {
+                let (parent, justification) = step(current2);
+                from_second.push((parent, current2, justification));
+                current2 = parent;
+                depth2 -= 1;
+            }
+        }
+        from_first.extend(from_second.into_iter().rev());
+        Some(from_first)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use serde::de::{self, Deserializer};
+    use serde::ser::Serializer;
+    use serde::{Deserialize, Serialize};
+
+    use super::ExplainingUnionFind;
+    use crate::UnionFind;
+
+    #[derive(Serialize, Deserialize)]
+    struct ExplainingRepr<J> {
+        union_find: UnionFind,
+        proofs: Vec<Option<(usize, J)>>,
+    }
+
+    impl<J: Serialize> Serialize for ExplainingUnionFind<J> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            #[derive(Serialize)]
+            struct ExplainingReprRef<'a, J> {
+                union_find: &'a UnionFind,
+                proofs: &'a [Option<(usize, J)>],
+            }
+            ExplainingReprRef {
+                union_find: &self.inner,
+                proofs: &self.proofs,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, J: Deserialize<'de>> Deserialize<'de> for ExplainingUnionFind<J> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = ExplainingRepr::<J>::deserialize(deserializer)?;
+            let len = repr.union_find.backing.len();
+            if repr.proofs.len() != len {
+                return Err(de::Error::custom(format!(
+                    "there are proofs for {} elements, rather than {len}",
+                    repr.proofs.len()
+                )));
+            }
+            // The proof edges must form a forest with the same sets as the
+            // structure, which we check by joining them all in a new structure.
+            let mut proven = UnionFind::new(len);
+            for (element, proof) in repr.proofs.iter().enumerate() {
+                if let Some((parent, _)) = *proof {
+                    if parent >= len || proven.union(element, parent).already_joined {
+                        return Err(de::Error::custom(format!(
+                            "the proof of element {element} is not part of a forest"
+                        )));
+                    }
+                }
+            }
+            if proven.labels() != repr.union_find.labels() {
+                return Err(de::Error::custom(
+                    "the proofs do not join the same sets as the structure",
+                ));
+            }
+            Ok(ExplainingUnionFind {
+                inner: repr.union_find,
+                proofs: repr.proofs,
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let mut explaining = ExplainingUnionFind::new(3);
+            explaining.union(0, 1, "given".to_string());
+            let explaining: ExplainingUnionFind<String> =
+                serde_json::from_str(&serde_json::to_string(&explaining).unwrap()).unwrap();
+            assert_eq!(
+                explaining.explain(1, 0),
+                Some(vec![(1, 0, "given".to_string())])
+            );
+        }
+
+        #[test]
+        fn inconsistent_proofs_are_rejected() {
+            assert!(serde_json::from_str::<ExplainingUnionFind<u32>>(
+                r#"{"union_find":{"parents":[0,0],"ranks":[1,0]},"proofs":[[1,0],[0,0]]}"#
+            )
+            .is_err());
+            assert!(serde_json::from_str::<ExplainingUnionFind<u32>>(
+                r#"{"union_find":{"parents":[0,0],"ranks":[1,0]},"proofs":[null,null]}"#
+            )
+            .is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that an explanation is a path of unions from one element to another.
+    fn assert_path(path: &[(usize, usize, u32)], from: usize, to: usize) {
+        let mut current = from;
+        for &(step_from, step_to, _) in path {
+            assert_eq!(step_from, current);
+            current = step_to;
+        }
+        assert_eq!(current, to);
+    }
+
+    #[test]
+    fn explanations_follow_unions() {
+        let mut uf = ExplainingUnionFind::new(6);
+        uf.union(0, 1, 10);
+        uf.union(2, 3, 11);
+        uf.union(3, 1, 12);
+        uf.union(4, 5, 13);
+        uf.union(2, 0, 14);
+        assert_eq!(
+            uf.explain(0, 2),
+            Some(vec![(0, 1, 10), (1, 3, 12), (3, 2, 11)])
+        );
+        assert_eq!(uf.explain(3, 3), Some(vec![]));
+        assert_eq!(uf.explain(5, 4), Some(vec![(5, 4, 13)]));
+        assert_eq!(uf.explain(0, 4), None);
+        assert_eq!(uf.explain(0, 6), None);
+        assert_eq!(uf.set_count(), 2);
+    }
+
+    #[test]
+    fn explanations_survive_rerooting() {
+        const SIZE: usize = 200;
+        let mut uf = ExplainingUnionFind::new(SIZE);
+        // Build two long chains, then join them in the middle so that one of
+        // them has to be rerooted.
+        for i in 1..SIZE / 2 {
+            uf.union(i - 1, i, i as u32);
+        }
+        for i in SIZE / 2 + 1..SIZE {
+            uf.union(i, i - 1, i as u32);
+        }
+        uf.union(SIZE / 4, 3 * SIZE / 4, 0);
+        for (from, to) in [(0, SIZE - 1), (SIZE - 1, 0), (SIZE / 2, SIZE / 2 - 1)] {
+            let path = uf.explain(from, to).unwrap();
+            assert_path(&path, from, to);
+        }
+        let path = uf.explain(SIZE / 4 - 1, 3 * SIZE / 4 + 1).unwrap();
+        assert_eq!(
+            path,
+            vec![
+                (SIZE / 4 - 1, SIZE / 4, (SIZE / 4) as u32),
+                (SIZE / 4, 3 * SIZE / 4, 0),
+                (3 * SIZE / 4, 3 * SIZE / 4 + 1, (3 * SIZE / 4 + 1) as u32),
+            ]
+        );
+    }
+
+    #[test]
+    fn fresh_and_out_of_bounds() {
+        let mut uf = ExplainingUnionFind::new(1);
+        assert!(uf.try_union(0, 1, 0).is_err());
+        assert_eq!(uf.fresh(), 1);
+        assert!(!uf.union(0, 1, 7).already_joined);
+        assert!(uf.union(1, 0, 8).already_joined);
+        assert_eq!(uf.explain(1, 0), Some(vec![(1, 0, 7)]));
+        assert_eq!(uf.size_of(0), Some(2));
+    }
+}
@@ -0,0 +1,505 @@
+//! A compact binary file format for [`UnionFind`], written by
+//! [`UnionFind::write_to`] and read by [`UnionFind::read_from`].
+//!
+//! Version 1 of the format is laid out as follows, with every multi-byte
+//! integer in little-endian order, and every varint in LEB128:
+//!
+//! | Field       | Encoding                                                     |
+//! |-------------|--------------------------------------------------------------|
+//! | magic       | the four bytes `UFND`                                        |
+//! | version     | one byte, currently `1`                                      |
+//! | flags       | one byte, with bit 0 set when ranks are present              |
+//! | compression | one byte naming the [`CompressionStrategy`]                  |
+//! | link policy | one byte naming the [`LinkPolicy`], then an eight byte seed for [`LinkPolicy::Randomized`] |
+//! | length      | a varint giving the number of elements                       |
+//! | width       | one byte giving the number of bits needed for the largest element |
+//! | parents     | each parent in `width` bits, least significant first, padded with zeros to a whole byte |
+//! | ranks       | if present, each rank as a varint                            |
+//! | checksum    | the CRC-32 of every preceding byte, in four bytes            |
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::{CompressionStrategy, Element, LinkPolicy, UnionFind, UnionFindError};
+
+const MAGIC: [u8; 4] = *b"UFND";
+const VERSION: u8 = 1;
+const FLAG_RANKS: u8 = 1;
+
+/// The ways in which reading a [`UnionFind`] with [`UnionFind::read_from`] can fail.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FormatError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// The input ended before the structure did.
+    Truncated,
+    /// The input does not start with the expected magic bytes.
+    BadMagic,
+    /// The input was written in a version of the format which is not supported.
+    UnsupportedVersion(u8),
+    /// A field of the input holds a value which the format does not allow.
+    Malformed(&'static str),
+    /// The checksum stored in the input does not match the checksum of its contents.
+    ChecksumMismatch { stored: u32, computed: u32 },
+    /// The input describes parent pointers or ranks which do not form a valid structure.
+    InvalidStructure(UnionFindError),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Io(err) => write!(f, "failed to read union find: {err}"),
+            FormatError::Truncated => write!(f, "union find file is truncated"),
+            FormatError::BadMagic => write!(f, "not a union find file"),
+            FormatError::UnsupportedVersion(version) => {
+                write!(f, "unsupported union find file version {version}")
+            }
+            FormatError::Malformed(reason) => write!(f, "malformed union find file: {reason}"),
+            FormatError::ChecksumMismatch { stored, computed } => write!(
+                f,
+                "union find file is corrupted: stored checksum {stored:08x}, computed {computed:08x}"
+            ),
+            FormatError::InvalidStructure(err) => write!(f, "invalid union find file: {err}"),
+        }
+    }
+}
+
+impl Error for FormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FormatError::Io(err) => Some(err),
+            FormatError::InvalidStructure(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FormatError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            FormatError::Truncated
+        } else {
+            FormatError::Io(err)
+        }
+    }
+}
+
+impl UnionFind {
+    /// Write this structure to the given writer in the format described in the
+    /// [`format`](crate::format) module, including the rank of every element.
+    ///
+    /// The writer is written to in many small pieces, so it should be buffered.
+    pub fn write_to(&self, writer: impl Write) -> io::Result<()> {
+        self.write(writer, true)
+    }
+
+    /// Write this structure to the given writer like [`UnionFind::write_to`], but
+    /// without ranks. When it is read back, every element is ranked by the height
+    /// of its tree, which is enough for [`UnionFind::union`] to stay efficient.
+    pub fn write_without_ranks_to(&self, writer: impl Write) -> io::Result<()> {
+        self.write(writer, false)
+    }
+
+    fn write(&self, writer: impl Write, ranks: bool) -> io::Result<()> {
+        let mut writer = ChecksumWriter {
+            inner: writer,
+            crc: Crc32::new(),
+        };
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION, if ranks { FLAG_RANKS } else { 0 }])?;
+        writer.write_all(&[compression_tag(self.compression)])?;
+        match self.link_policy {
+            LinkPolicy::ByRank => writer.write_all(&[0])?,
+            LinkPolicy::BySize => writer.write_all(&[1])?,
+            LinkPolicy::Randomized(seed) => {
+                writer.write_all(&[2])?;
+                writer.write_all(&seed.to_le_bytes())?;
+            }
+            LinkPolicy::ByIndex => writer.write_all(&[3])?,
+        }
+        let len = self.backing.len();
+        write_varint(&mut writer, len as u64)?;
+        let width = width_for(len);
+        writer.write_all(&[width])?;
+
+        // Parents are packed into a bit stream, emitting each byte once full.
+        let mut buffer: u128 = 0;
+        let mut bits = 0;
+        for element in &self.backing {
+            buffer |= (element.parent as u128) << bits;
+            bits += u32::from(width);
+            while bits >= 8 {
+                writer.write_all(&[buffer as u8])?;
+                buffer >>= 8;
+                bits -= 8;
+            }
+        }
+        if bits > 0 {
+            writer.write_all(&[buffer as u8])?;
+        }
+
+        if ranks {
+            for element in &self.backing {
+                write_varint(&mut writer, element.rank as u64)?;
+            }
+        }
+        let checksum = writer.crc.finish();
+        writer.inner.write_all(&checksum.to_le_bytes())?;
+        writer.inner.flush()
+    }
+
+    /// Read a structure written by [`UnionFind::write_to`] from the given reader,
+    /// rejecting input which is truncated, corrupted, or describes an invalid structure.
+    ///
+    /// The reader is read from in many small pieces, so it should be buffered.
+    pub fn read_from(reader: impl Read) -> Result<UnionFind, FormatError> {
+        let mut reader = ChecksumReader {
+            inner: reader,
+            crc: Crc32::new(),
+        };
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(FormatError::BadMagic);
+        }
+        let version = read_byte(&mut reader)?;
+        if version != VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+        let flags = read_byte(&mut reader)?;
+        if flags & !FLAG_RANKS != 0 {
+            return Err(FormatError::Malformed("unknown flags are set"));
+        }
+        let compression = match read_byte(&mut reader)? {
+            0 => CompressionStrategy::Full,
+            1 => CompressionStrategy::Halving,
+            2 => CompressionStrategy::Splitting,
+            3 => CompressionStrategy::None,
+            _ => return Err(FormatError::Malformed("unknown compression strategy")),
+        };
+        let link_policy = match read_byte(&mut reader)? {
+            0 => LinkPolicy::ByRank,
+            1 => LinkPolicy::BySize,
+            2 => {
+                let mut seed = [0; 8];
+                reader.read_exact(&mut seed)?;
+                LinkPolicy::Randomized(u64::from_le_bytes(seed))
+            }
+            3 => LinkPolicy::ByIndex,
+            _ => return Err(FormatError::Malformed("unknown link policy")),
+        };
+        let len = usize::try_from(read_varint(&mut reader)?)
+            .map_err(|_| FormatError::Malformed("too many elements for this platform"))?;
+        let width = read_byte(&mut reader)?;
+        if width != width_for(len) {
+            return Err(FormatError::Malformed("parent width does not match length"));
+        }
+
+        // We never trust the length enough to allocate for all of it up front,
+        // so that a corrupted length fails as truncated input rather than by
+        // exhausting memory.
+        let capacity = len.min(1 << 20);
+        let mut parents = Vec::with_capacity(capacity);
+        let mask = if width == 0 {
+            0
+        } else {
+            u128::MAX >> (128 - u32::from(width))
+        };
+        let mut buffer: u128 = 0;
+        let mut bits = 0;
+        while parents.len() < len {
+            while bits < u32::from(width) {
+                buffer |= u128::from(read_byte(&mut reader)?) << bits;
+                bits += 8;
+            }
+            parents.push((buffer & mask) as usize);
+            buffer >>= width;
+            bits -= u32::from(width);
+        }
+        if buffer != 0 {
+            return Err(FormatError::Malformed("padding bits are set"));
+        }
+
+        let ranks = if flags & FLAG_RANKS != 0 {
+            let mut ranks = Vec::with_capacity(capacity);
+            for _ in 0..len {
+                let rank = usize::try_from(read_varint(&mut reader)?)
+                    .map_err(|_| FormatError::Malformed("rank is too large"))?;
+                ranks.push(rank);
+            }
+            Some(ranks)
+        } else {
+            None
+        };
+
+        let computed = reader.crc.finish();
+        let mut stored = [0; 4];
+        reader.inner.read_exact(&mut stored)?;
+        let stored = u32::from_le_bytes(stored);
+        if stored != computed {
+            return Err(FormatError::ChecksumMismatch { stored, computed });
+        }
+
+        let backing = Element::forest(parents, ranks).map_err(FormatError::InvalidStructure)?;
+        Ok(UnionFind {
+            set_count: Element::count_roots(&backing),
+            backing,
+            compression,
+            link_policy,
+        })
+    }
+}
+
+fn compression_tag(compression: CompressionStrategy) -> u8 {
+    match compression {
+        CompressionStrategy::Full => 0,
+        CompressionStrategy::Halving => 1,
+        CompressionStrategy::Splitting => 2,
+        CompressionStrategy::None => 3,
+    }
+}
+
+/// The number of bits needed to store every element of a structure of this length.
+fn width_for(len: usize) -> u8 {
+    (usize::BITS - len.saturating_sub(1).leading_zeros()) as u8
+}
+
+fn write_varint(writer: &mut impl Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint(reader: &mut impl Read) -> Result<u64, FormatError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_byte(reader)?;
+        let bits = u64::from(byte & 0x7f);
+        if bits << shift >> shift != bits {
+            break;
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(FormatError::Malformed("varint does not fit in 64 bits"))
+}
+
+fn read_byte(reader: &mut impl Read) -> Result<u8, FormatError> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// The CRC-32 used by zlib, PNG and many others.
+struct Crc32 {
+    state: u32,
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+impl Crc32 {
+    fn new() -> Self {
+        Crc32 { state: !0 }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state =
+                CRC_TABLE[((self.state ^ u32::from(byte)) & 0xff) as usize] ^ (self.state >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+
+struct ChecksumWriter<W> {
+    inner: W,
+    crc: Crc32,
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.crc.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct ChecksumReader<R> {
+    inner: R,
+    crc: Crc32,
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.crc.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(policy: LinkPolicy) -> UnionFind {
+        let mut uf = UnionFind::new(1000)
+            .with_compression(CompressionStrategy::Halving)
+            .with_link_policy(policy);
+        for i in 0..1000 {
+            uf.union(i, (i * i + 7) % 1000);
+        }
+        uf
+    }
+
+    fn parts(uf: &UnionFind) -> Vec<(usize, usize)> {
+        uf.backing.iter().map(|e| (e.parent, e.rank)).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        for policy in [
+            LinkPolicy::ByRank,
+            LinkPolicy::Randomized(99),
+            LinkPolicy::ByIndex,
+        ] {
+            let uf = sample(policy);
+            let mut bytes = Vec::new();
+            uf.write_to(&mut bytes).unwrap();
+            let mut back = UnionFind::read_from(&bytes[..]).unwrap();
+            assert_eq!(parts(&back), parts(&uf));
+            assert_eq!(back.compression(), uf.compression());
+            assert_eq!(back.link_policy(), policy);
+            assert_eq!(back.set_count(), uf.set_count());
+            assert_eq!(back.groups(), uf.groups());
+            assert_eq!(
+                back.size_of(7),
+                uf.find_immutable(7).map(|rep| uf.backing[rep].size)
+            );
+        }
+        for len in [0, 1, 2] {
+            let mut bytes = Vec::new();
+            UnionFind::new(len).write_to(&mut bytes).unwrap();
+            assert_eq!(UnionFind::read_from(&bytes[..]).unwrap().set_count(), len);
+        }
+    }
+
+    #[test]
+    fn round_trip_without_ranks() {
+        let uf = sample(LinkPolicy::ByRank);
+        let mut with_ranks = Vec::new();
+        uf.write_to(&mut with_ranks).unwrap();
+        let mut bytes = Vec::new();
+        uf.write_without_ranks_to(&mut bytes).unwrap();
+        assert!(bytes.len() < with_ranks.len());
+        let mut back = UnionFind::read_from(&bytes[..]).unwrap();
+        assert_eq!(back.groups(), uf.groups());
+        back.union(0, 1);
+    }
+
+    #[test]
+    fn truncated_input_is_rejected() {
+        let mut bytes = Vec::new();
+        sample(LinkPolicy::ByRank).write_to(&mut bytes).unwrap();
+        for len in [0, 3, 10, bytes.len() / 2, bytes.len() - 1] {
+            assert!(matches!(
+                UnionFind::read_from(&bytes[..len]),
+                Err(FormatError::Truncated)
+            ));
+        }
+    }
+
+    #[test]
+    fn tampered_input_is_rejected() {
+        let mut bytes = Vec::new();
+        sample(LinkPolicy::ByRank).write_to(&mut bytes).unwrap();
+        let last = bytes.len() - 5;
+        bytes[last] ^= 0x10;
+        assert!(matches!(
+            UnionFind::read_from(&bytes[..]),
+            Err(FormatError::ChecksumMismatch { .. })
+        ));
+
+        let mut bytes = Vec::new();
+        UnionFind::new(4).write_to(&mut bytes).unwrap();
+        bytes[0] = b'X';
+        assert!(matches!(
+            UnionFind::read_from(&bytes[..]),
+            Err(FormatError::BadMagic)
+        ));
+        bytes[0] = b'U';
+        bytes[4] = 2;
+        assert!(matches!(
+            UnionFind::read_from(&bytes[..]),
+            Err(FormatError::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn invalid_structure_is_rejected() {
+        // Two elements pointing at each other, with a correct checksum.
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([VERSION, 0, 0, 0, 2, 1, 0b01]);
+        let mut crc = Crc32::new();
+        crc.update(&bytes);
+        bytes.extend(crc.finish().to_le_bytes());
+        assert!(matches!(
+            UnionFind::read_from(&bytes[..]),
+            Err(FormatError::InvalidStructure(UnionFindError::Cycle { .. }))
+        ));
+
+        // Two roots, one with a rank that linking could never produce.
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([VERSION, FLAG_RANKS, 0, 1, 2, 1, 0b10, 0]);
+        write_varint(&mut bytes, u64::MAX).unwrap();
+        let mut crc = Crc32::new();
+        crc.update(&bytes);
+        bytes.extend(crc.finish().to_le_bytes());
+        assert!(matches!(
+            UnionFind::read_from(&bytes[..]),
+            Err(FormatError::InvalidStructure(UnionFindError::InvalidRank {
+                element: 1
+            }))
+        ));
+    }
+
+    #[test]
+    fn crc_matches_reference() {
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xcbf43926);
+    }
+}
@@ -0,0 +1,208 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::UnionFind;
+
+/// A [`KeyedUnionFind`] maintains arbitrary hashable keys, each in a disjoint set.
+///
+/// Keys are interned into a [`UnionFind`] the first time they are seen, so both
+/// structures share the same underlying engine.
+pub struct KeyedUnionFind<K> {
+    inner: UnionFind,
+    ids: HashMap<K, usize>,
+    keys: Vec<K>,
+}
+
+impl<K: Hash + Eq + Clone> KeyedUnionFind<K> {
+    /// Construct a new, empty [`KeyedUnionFind`].
+    pub fn new() -> Self {
+        KeyedUnionFind {
+            inner: UnionFind::new(0),
+            ids: HashMap::new(),
+            keys: Vec::new(),
+        }
+    }
+
+    /// The number of keys which have been interned so far.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether no keys have been interned yet.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Whether this key has been interned.
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.ids.contains_key(key)
+    }
+
+    /// Intern a key in its own set if it has not been seen before, returning
+    /// the index it occupies in the underlying [`UnionFind`].
+    pub fn insert(&mut self, key: &K) -> usize {
+        if let Some(&id) = self.ids.get(key) {
+            return id;
+        }
+        let id = self.inner.fresh();
+        self.ids.insert(key.clone(), id);
+        self.keys.push(key.clone());
+        id
+    }
+
+    /// Find the representative key for the set that this key belongs to, or
+    /// [`None`] if the key has never been seen.
+    pub fn find<Q>(&mut self, key: &Q) -> Option<K>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let id = *self.ids.get(key)?;
+        let rep = self.inner.find(id)?;
+        Some(self.keys[rep].clone())
+    }
+
+    /// The number of disjoint sets currently in the structure.
+    pub fn set_count(&self) -> usize {
+        self.inner.set_count()
+    }
+
+    /// Cause the union of the sets which two keys belong to, interning either
+    /// of them if they have not been seen before, and returning whether two
+    /// distinct sets were actually merged.
+    pub fn union(&mut self, key1: &K, key2: &K) -> bool {
+        let id1 = self.insert(key1);
+        let id2 = self.insert(key2);
+        !self.inner.union(id1, id2).already_joined
+    }
+}
+
+impl<K: Hash + Eq + Clone> Default for KeyedUnionFind<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use std::collections::HashMap;
+    use std::hash::Hash;
+
+    use serde::de::{self, Deserializer};
+    use serde::ser::Serializer;
+    use serde::{Deserialize, Serialize};
+
+    use super::KeyedUnionFind;
+    use crate::UnionFind;
+
+    #[derive(Serialize, Deserialize)]
+    struct KeyedRepr<K> {
+        keys: Vec<K>,
+        union_find: UnionFind,
+    }
+
+    impl<K: Serialize> Serialize for KeyedUnionFind<K> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            #[derive(Serialize)]
+            struct KeyedReprRef<'a, K> {
+                keys: &'a [K],
+                union_find: &'a UnionFind,
+            }
+            KeyedReprRef {
+                keys: &self.keys,
+                union_find: &self.inner,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, K: Deserialize<'de> + Hash + Eq + Clone> Deserialize<'de> for KeyedUnionFind<K> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = KeyedRepr::<K>::deserialize(deserializer)?;
+            if repr.keys.len() != repr.union_find.backing.len() {
+                return Err(de::Error::custom(format!(
+                    "there are {} keys for {} elements",
+                    repr.keys.len(),
+                    repr.union_find.backing.len()
+                )));
+            }
+            let mut ids = HashMap::with_capacity(repr.keys.len());
+            for (id, key) in repr.keys.iter().enumerate() {
+                if ids.insert(key.clone(), id).is_some() {
+                    return Err(de::Error::custom(format!("key {id} is a duplicate")));
+                }
+            }
+            Ok(KeyedUnionFind {
+                inner: repr.union_find,
+                ids,
+                keys: repr.keys,
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let mut keyed = KeyedUnionFind::new();
+            keyed.union(&"a".to_string(), &"b".to_string());
+            let mut keyed: KeyedUnionFind<String> =
+                serde_json::from_str(&serde_json::to_string(&keyed).unwrap()).unwrap();
+            assert_eq!(keyed.find("a"), keyed.find("b"));
+        }
+
+        #[test]
+        fn duplicate_keys_are_rejected() {
+            assert!(serde_json::from_str::<KeyedUnionFind<String>>(
+                r#"{"keys":["a","a"],"union_find":{"parents":[0,1],"ranks":[0,0]}}"#
+            )
+            .is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union_interns_keys() {
+        let mut uf = KeyedUnionFind::new();
+        assert!(uf.union(&"a", &"b"));
+        assert!(!uf.union(&"b", &"a"));
+        assert_eq!(uf.set_count(), 1);
+        assert!(uf.contains(&"a"));
+        assert!(uf.contains(&"b"));
+        assert!(!uf.contains(&"c"));
+        assert_eq!(uf.len(), 2);
+        assert_eq!(uf.find(&"a"), uf.find(&"b"));
+    }
+
+    #[test]
+    fn find_unknown_key() {
+        let mut uf: KeyedUnionFind<String> = KeyedUnionFind::new();
+        assert_eq!(uf.find("missing"), None);
+        uf.union(&"a".to_string(), &"b".to_string());
+        assert!(uf.contains("a"));
+        assert_eq!(uf.find("a"), uf.find("b"));
+    }
+
+    #[test]
+    fn representative_is_a_member() {
+        let mut uf = KeyedUnionFind::new();
+        uf.union(&1, &2);
+        uf.union(&3, &4);
+        let rep = uf.find(&1).unwrap();
+        assert!(rep == 1 || rep == 2);
+        assert_ne!(uf.find(&1), uf.find(&3));
+        uf.insert(&5);
+        assert_eq!(uf.find(&5), Some(5));
+    }
+}
@@ -0,0 +1,264 @@
+//! Kruskal's algorithm for spanning forests of weighted graphs, built on
+//! [`UnionFind`].
+//!
+//! Edges are given as `(from, to, weight)` triples over vertices numbered from
+//! zero, with any [`Ord`] weight:
+//!
+//! ```
+//! use union_find::kruskal;
+//!
+//! let edges = vec![(0, 1, 4), (1, 2, 1), (0, 2, 2), (2, 3, 7)];
+//! let forest = kruskal::minimum_spanning_forest(4, edges);
+//!
+//! assert_eq!(forest.edges, vec![(1, 2, 1), (0, 2, 2), (2, 3, 7)]);
+//! assert_eq!(forest.total(), 10);
+//! ```
+
+use std::cmp::Ordering;
+use std::iter::Sum;
+
+use crate::{UnionFind, UnionFindError};
+
+/// Whether a spanning forest should have the least or the greatest total weight.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Objective {
+    /// Prefer lighter edges.
+    #[default]
+    Minimum,
+    /// Prefer heavier edges.
+    Maximum,
+}
+
+/// A spanning forest, as computed by [`Kruskal::run`].
+pub struct SpanningForest<W> {
+    /// The edges of the forest, in the order they were chosen.
+    pub edges: Vec<(usize, usize, W)>,
+    /// The vertices, joined by the chosen edges.
+    pub components: UnionFind,
+}
+
+impl<W> SpanningForest<W> {
+    /// The sum of the weights of the chosen edges.
+    pub fn total<'a>(&'a self) -> W
+    where
+        W: Sum<&'a W>,
+    {
+        self.edges.iter().map(|(_, _, weight)| weight).sum()
+    }
+}
+
+/// Kruskal's algorithm over a fixed number of vertices.
+///
+/// By default this computes a minimum spanning forest, but it can be asked for a
+/// maximum one with [`Kruskal::with_objective`], or to stop as soon as only a
+/// given number of components remain with [`Kruskal::with_components`], which
+/// is single-linkage clustering.
+#[derive(Clone, Copy, Debug)]
+pub struct Kruskal {
+    len: usize,
+    objective: Objective,
+    components: usize,
+}
+
+impl Kruskal {
+    /// Prepare to compute a spanning forest over the given number of vertices.
+    pub fn new(len: usize) -> Self {
+        Kruskal {
+            len,
+            objective: Objective::default(),
+            components: 1,
+        }
+    }
+
+    /// Choose whether the forest should have the least or greatest total weight.
+    pub fn with_objective(mut self, objective: Objective) -> Self {
+        self.objective = objective;
+        self
+    }
+
+    /// Stop adding edges once the forest has this many components.
+    pub fn with_components(mut self, components: usize) -> Self {
+        self.components = components;
+        self
+    }
+
+    /// Compute the spanning forest of a graph with the given edges. Among edges
+    /// of equal weight, those given first are preferred.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either end of any edge is not a vertex of the graph, even if
+    /// the forest is complete before that edge would be considered.
+    pub fn run<W, I>(&self, edges: I) -> SpanningForest<W>
+    where
+        W: Ord,
+        I: IntoIterator<Item = (usize, usize, W)>,
+    {
+        let mut edges: Vec<_> = edges.into_iter().collect();
+        for &(from, to, _) in &edges {
+            if let Some(element) = [from, to].into_iter().find(|&end| end >= self.len) {
+                let err = UnionFindError::OutOfBounds {
+                    element,
+                    len: self.len,
+                };
+                panic!("{err}");
+            }
+        }
+        let order = |a: &W, b: &W| -> Ordering {
+            match self.objective {
+                Objective::Minimum => a.cmp(b),
+                Objective::Maximum => b.cmp(a),
+            }
+        };
+        edges.sort_by(|(_, _, a), (_, _, b)| order(a, b));
+
+        let mut components = UnionFind::new(self.len);
+        let mut chosen = Vec::new();
+        for (from, to, weight) in edges {
+            if components.set_count() <= self.components {
+                break;
+            }
+            if !components.union(from, to).already_joined {
+                chosen.push((from, to, weight));
+            }
+        }
+        SpanningForest {
+            edges: chosen,
+            components,
+        }
+    }
+}
+
+/// Compute a minimum spanning forest of a graph over the given number of vertices.
+///
+/// # Panics
+///
+/// Panics if either end of an edge is not a vertex of the graph.
+pub fn minimum_spanning_forest<W, I>(len: usize, edges: I) -> SpanningForest<W>
+where
+    W: Ord,
+    I: IntoIterator<Item = (usize, usize, W)>,
+{
+    Kruskal::new(len).run(edges)
+}
+
+/// Compute a maximum spanning forest of a graph over the given number of vertices.
+///
+/// # Panics
+///
+/// Panics if either end of an edge is not a vertex of the graph.
+pub fn maximum_spanning_forest<W, I>(len: usize, edges: I) -> SpanningForest<W>
+where
+    W: Ord,
+    I: IntoIterator<Item = (usize, usize, W)>,
+{
+    Kruskal::new(len)
+        .with_objective(Objective::Maximum)
+        .run(edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Reverse;
+
+    use super::*;
+
+    fn graph() -> Vec<(usize, usize, u32)> {
+        vec![
+            (0, 1, 7),
+            (0, 3, 5),
+            (1, 2, 8),
+            (1, 3, 9),
+            (1, 4, 7),
+            (2, 4, 5),
+            (3, 4, 15),
+            (3, 5, 6),
+            (4, 5, 8),
+            (4, 6, 9),
+            (5, 6, 11),
+        ]
+    }
+
+    #[test]
+    fn minimum_spanning_tree() {
+        let forest = minimum_spanning_forest(7, graph());
+        assert_eq!(forest.total(), 39);
+        assert_eq!(forest.edges.len(), 6);
+        assert_eq!(forest.components.set_count(), 1);
+        assert_eq!(
+            forest.edges,
+            vec![
+                (0, 3, 5),
+                (2, 4, 5),
+                (3, 5, 6),
+                (0, 1, 7),
+                (1, 4, 7),
+                (4, 6, 9)
+            ]
+        );
+    }
+
+    #[test]
+    fn maximum_spanning_tree() {
+        let forest = maximum_spanning_forest(7, graph());
+        assert_eq!(forest.total(), 59);
+        assert_eq!(forest.edges.len(), 6);
+        assert_eq!(forest.components.set_count(), 1);
+    }
+
+    #[test]
+    fn disconnected_graphs_give_forests() {
+        let forest = minimum_spanning_forest(5, vec![(0, 1, 3), (1, 2, 1), (0, 2, 2)]);
+        assert_eq!(forest.edges, vec![(1, 2, 1), (0, 2, 2)]);
+        assert_eq!(forest.total(), 3);
+        assert_eq!(forest.components.set_count(), 3);
+
+        let empty = minimum_spanning_forest::<u32, _>(0, vec![]);
+        assert_eq!(empty.total(), 0);
+        assert!(empty.edges.is_empty());
+    }
+
+    #[test]
+    fn stops_at_components() {
+        let forest = Kruskal::new(7).with_components(3).run(graph());
+        assert_eq!(
+            forest.edges,
+            vec![(0, 3, 5), (2, 4, 5), (3, 5, 6), (0, 1, 7)]
+        );
+        assert_eq!(forest.total(), 23);
+        let mut components = forest.components;
+        assert_eq!(components.set_count(), 3);
+        assert!(components.same_set(1, 5));
+        assert!(!components.same_set(1, 2));
+        assert_eq!(components.size_of(6), Some(1));
+    }
+
+    #[test]
+    fn weights_need_only_be_ordered() {
+        // Prefer fewer hops, and then the alphabetically earlier route.
+        let forest = minimum_spanning_forest(
+            3,
+            vec![(0, 1, (2, "a")), (1, 2, (1, "z")), (0, 2, (1, "m"))],
+        );
+        assert_eq!(forest.edges, vec![(0, 2, (1, "m")), (1, 2, (1, "z"))]);
+
+        let reversed = graph()
+            .into_iter()
+            .filter(|&(from, to, _)| from < 3 && to < 3)
+            .map(|(from, to, weight)| (from, to, Reverse(weight)));
+        let forest = minimum_spanning_forest(3, reversed);
+        assert_eq!(forest.edges, vec![(1, 2, Reverse(8)), (0, 1, Reverse(7))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn out_of_range_edges_panic() {
+        minimum_spanning_forest(2, vec![(0, 2, 1)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn out_of_range_edges_panic_after_stopping() {
+        Kruskal::new(3).run(vec![(0, 1, 1), (1, 2, 2), (2, 3, 3)]);
+    }
+}
@@ -24,17 +24,248 @@
 //!
 //! assert_eq!(uf.find(0).unwrap(), uf.find(1).unwrap());
 //! ```
+//!
+//! The way [`UnionFind::find`] shortens the paths it walks can be chosen with a
+//! [`CompressionStrategy`]:
+//!
+//! ```
+//! use union_find::{CompressionStrategy, UnionFind};
+//!
+//! let mut uf = UnionFind::new(10).with_compression(CompressionStrategy::Halving);
+//!
+//! uf.union(0, 1);
+//!
+//! assert_eq!(uf.find(0).unwrap(), uf.find(1).unwrap());
+//! ```
+//!
+//! If your elements are not naturally numbered, [`KeyedUnionFind`] offers the
+//! same interface over arbitrary hashable keys:
+//!
+//! ```
+//! use union_find::KeyedUnionFind;
+//!
+//! let mut uf = KeyedUnionFind::new();
+//!
+//! uf.union(&"a", &"b");
+//!
+//! assert_eq!(uf.find(&"a"), uf.find(&"b"));
+//! assert!(!uf.contains(&"c"));
+//! ```
+//!
+//! Structures can be saved to and loaded from a compact binary file format with
+//! [`UnionFind::write_to`] and [`UnionFind::read_from`], described in [`format`].
+//!
+//! For graphs given as lists of edges or of neighbours, [`connected_components`]
+//! and [`connected_components_from_adjacency`] group their vertices, and the
+//! [`kruskal`] module computes minimum and maximum spanning forests. The
+//! [`congruence`] module decides equalities between ground terms,
+//! and the [`egraph`] module goes further, representing many equal terms at once.
+//! The [`unify`] module unifies terms with variables, as in type inference.
+//!
+//! With the `serde` feature enabled, every structure in this crate, including
+//! congruence closures, e-graphs and unifiers, implements `Serialize` and
+//! `Deserialize`.
+
+use std::collections::HashMap;
+use std::mem;
+
+mod cell;
+mod concurrent;
+pub mod congruence;
+pub mod egraph;
+mod error;
+mod explain;
+pub mod format;
+mod keyed;
+pub mod kruskal;
+mod members;
+mod naive;
+mod persistent;
+mod rollback;
+#[cfg(feature = "serde")]
+mod serialize;
+mod strategy;
+pub mod unify;
+mod weighted;
+mod with;
+
+pub use cell::CellUnionFind;
+pub use concurrent::ConcurrentUnionFind;
+pub use error::UnionFindError;
+pub use explain::ExplainingUnionFind;
+pub use format::FormatError;
+pub use keyed::KeyedUnionFind;
+pub use members::Members;
+pub use naive::NaiveUnionFind;
+pub use persistent::PersistentUnionFind;
+pub use rollback::{RollbackUnionFind, Snapshot};
+pub use strategy::{CompressionStrategy, LinkPolicy};
+pub use weighted::{Group, WeightedUnionFind};
+pub use with::{Merge, UnionFindWith};
 
 /// A [`UnionFind`] structure allows you to maintain items
 /// indexed by natural numbers, each in a disjoint set.
 pub struct UnionFind {
     backing: Vec<Element>,
+    compression: CompressionStrategy,
+    link_policy: LinkPolicy,
+    set_count: usize,
 }
 
 #[derive(Clone, Copy, Debug)]
 struct Element {
     parent: usize,
     rank: usize,
+    /// The number of elements in this set, only meaningful at representatives.
+    size: usize,
+    /// The next element in a circular list threading through every member of this set.
+    next: usize,
+}
+
+impl Element {
+    /// Rebuild the elements of a forest from its parent pointers, along with the
+    /// ranks of its elements if they are known, checking that every parent is in
+    /// bounds, that no parents form a cycle, and that parents outrank their children.
+    ///
+    /// When no ranks are given, every element is ranked by the height of its tree.
+    fn forest(
+        parents: Vec<usize>,
+        ranks: Option<Vec<usize>>,
+    ) -> Result<Vec<Element>, UnionFindError> {
+        let len = parents.len();
+        for (element, &parent) in parents.iter().enumerate() {
+            if parent >= len {
+                return Err(UnionFindError::InvalidParent { element, parent });
+            }
+        }
+        if let Some(ranks) = &ranks {
+            if ranks.len() != len {
+                return Err(UnionFindError::InvalidRank {
+                    element: ranks.len().min(len),
+                });
+            }
+        }
+
+        // We visit children before their parents, computing heights along the
+        // way. Anything we never get to visit must lie on a cycle.
+        let mut children = vec![0usize; len];
+        for (element, &parent) in parents.iter().enumerate() {
+            if parent != element {
+                children[parent] += 1;
+            }
+        }
+        let mut order: Vec<usize> = (0..len).filter(|&i| children[i] == 0).collect();
+        let mut heights = vec![0usize; len];
+        let mut visited = 0;
+        while visited < order.len() {
+            let element = order[visited];
+            visited += 1;
+            let parent = parents[element];
+            if parent != element {
+                heights[parent] = heights[parent].max(heights[element] + 1);
+                children[parent] -= 1;
+                if children[parent] == 0 {
+                    order.push(parent);
+                }
+            }
+        }
+        if let Some(element) = (0..len).find(|&i| children[i] != 0) {
+            return Err(UnionFindError::Cycle { element });
+        }
+        let ranks = match ranks {
+            Some(ranks) => {
+                // Linking keeps every rank below the number of elements, which
+                // in turn keeps the ranks from overflowing.
+                if let Some(element) = ranks.iter().position(|&rank| rank >= len) {
+                    return Err(UnionFindError::InvalidRank { element });
+                }
+                for (element, &parent) in parents.iter().enumerate() {
+                    if parent != element && ranks[parent] <= ranks[element] {
+                        return Err(UnionFindError::InvalidRank { element });
+                    }
+                }
+                ranks
+            }
+            None => heights,
+        };
+
+        let mut backing: Vec<Element> = parents
+            .into_iter()
+            .zip(ranks)
+            .enumerate()
+            .map(|(i, (parent, rank))| Element {
+                parent,
+                rank,
+                size: 1,
+                next: i,
+            })
+            .collect();
+        // Visiting parents before their children, we can find every root in a
+        // single pass, then tally its size and thread its members together.
+        let mut roots = vec![0; len];
+        for &element in order.iter().rev() {
+            let parent = backing[element].parent;
+            roots[element] = if parent == element {
+                element
+            } else {
+                roots[parent]
+            };
+        }
+        for (element, &root) in roots.iter().enumerate() {
+            if root != element {
+                backing[root].size += 1;
+                backing[element].next = backing[root].next;
+                backing[root].next = element;
+            }
+        }
+        Ok(backing)
+    }
+
+    /// The number of representatives, and so of sets, in a forest.
+    fn count_roots(backing: &[Element]) -> usize {
+        backing
+            .iter()
+            .enumerate()
+            .filter(|&(i, e)| e.parent == i)
+            .count()
+    }
+
+    /// Point the representative `child` at the representative `parent`.
+    fn link(backing: &mut [Element], child: usize, parent: usize) {
+        let (mut child_root, mut parent_root) = (backing[child], backing[parent]);
+        Element::link_roots(&mut child_root, &mut parent_root, parent);
+        backing[child] = child_root;
+        backing[parent] = parent_root;
+    }
+
+    /// Link the root `child` below the root `parent`, whose index is `parent_id`.
+    fn link_roots(child: &mut Element, parent: &mut Element, parent_id: usize) {
+        child.parent = parent_id;
+        // We maintain the rank as an upper bound on the height of the tree
+        // whichever policy is in use, so that policies may be swapped freely.
+        parent.rank = parent.rank.max(child.rank + 1);
+        parent.size += child.size;
+        // Swapping the successors of two elements in disjoint circular lists
+        // splices them together into a single circular list.
+        mem::swap(&mut child.next, &mut parent.next);
+    }
+}
+
+/// A description of what happened during a call to [`UnionFind::union`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnionOutcome {
+    /// Whether the two elements were already in the same set, in which case
+    /// nothing was changed.
+    pub already_joined: bool,
+    /// The representative of the first element's set before the union.
+    pub rep1: usize,
+    /// The representative of the second element's set before the union.
+    pub rep2: usize,
+    /// The representative of the merged set.
+    pub representative: usize,
+    /// The number of elements in the merged set.
+    pub size: usize,
 }
 
 impl UnionFind {
@@ -42,7 +273,17 @@ impl UnionFind {
     /// each in their own set.
     pub fn new(size: usize) -> Self {
         UnionFind {
-            backing: (0..size).map(|i| Element { parent: i, rank: 0 }).collect(),
+            backing: (0..size)
+                .map(|i| Element {
+                    parent: i,
+                    rank: 0,
+                    size: 1,
+                    next: i,
+                })
+                .collect(),
+            compression: CompressionStrategy::default(),
+            link_policy: LinkPolicy::default(),
+            set_count: size,
         }
     }
 
@@ -52,122 +293,727 @@ impl UnionFind {
         self.backing.push(Element {
             parent: fresh,
             rank: 0,
+            size: 1,
+            next: fresh,
         });
+        self.set_count += 1;
         fresh
     }
 
+    /// Add fresh elements, each in their own set, until the structure holds the
+    /// given number of elements.
+    fn grow_to(&mut self, len: usize) {
+        let old_len = self.backing.len();
+        if len > old_len {
+            self.backing.extend((old_len..len).map(|i| Element {
+                parent: i,
+                rank: 0,
+                size: 1,
+                next: i,
+            }));
+            self.set_count += len - old_len;
+        }
+    }
+
+    /// Use the given [`CompressionStrategy`] for all future calls to [`UnionFind::find`].
+    pub fn with_compression(mut self, compression: CompressionStrategy) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// The [`CompressionStrategy`] used by [`UnionFind::find`].
+    pub fn compression(&self) -> CompressionStrategy {
+        self.compression
+    }
+
+    /// Use the given [`LinkPolicy`] for all future calls to [`UnionFind::union`].
+    pub fn with_link_policy(mut self, link_policy: LinkPolicy) -> Self {
+        self.link_policy = link_policy;
+        self
+    }
+
+    /// The [`LinkPolicy`] used by [`UnionFind::union`].
+    pub fn link_policy(&self) -> LinkPolicy {
+        self.link_policy
+    }
+
     /// Find the representative for the set that this element belongs to.
     pub fn find(&mut self, element_id: usize) -> Option<usize> {
         if element_id >= self.backing.len() {
             None
         } else {
-            let mut current = element_id;
-            // First, we loop through the pointer structure starting at our element_id
-            // and find the root, which is an element which points to itself.
-            loop {
-                let element = self.backing[element_id];
-                // If the current element's parent is equal to itself, it is by
-                // definition the root.
-                if element.parent == current {
-                    break;
-                }
-                // Otherwise, we set current equal to the parent and continue
-                // the loop.
-                current = element.parent;
-            }
-            let rep = current;
-            current = element_id;
-            // Next, we loop through the pointer structure again, updating each element
-            // on the way to point to the representative of our group. This way, in the
-            // future, this will complete much faster.
-            loop {
-                let element = self.backing[current];
-                // If the current node is equal to its parent, then we have
-                // reached the representative element for this set.
-                if current == element.parent {
-                    break;
-                }
-                // Otherwise, we set the parent to be the representative element,
-                // maintaining the previous rank, update current to be equal to the
-                // parent, and continue the loop.
-                self.backing[current].parent = rep;
-                current = element.parent;
+            Some(match self.compression {
+                CompressionStrategy::Full => self.find_full(element_id),
+                CompressionStrategy::Halving => self.find_halving(element_id),
+                CompressionStrategy::Splitting => self.find_splitting(element_id),
+                CompressionStrategy::None => self.find_uncompressed(element_id),
+            })
+        }
+    }
+
+    /// Find the representative for the set that this element belongs to without
+    /// compressing any paths, so that only shared access is needed.
+    pub fn find_immutable(&self, element_id: usize) -> Option<usize> {
+        if element_id >= self.backing.len() {
+            None
+        } else {
+            Some(self.find_uncompressed(element_id))
+        }
+    }
+
+    /// Whether two elements belong to the same set, computed without compressing
+    /// any paths. Elements which are not in the structure belong to no set.
+    pub fn same_set(&self, element1: usize, element2: usize) -> bool {
+        match (self.find_immutable(element1), self.find_immutable(element2)) {
+            (Some(rep1), Some(rep2)) => rep1 == rep2,
+            _ => false,
+        }
+    }
+
+    /// Find the representative for the set that this element belongs to,
+    /// reporting an error if the element is not in the structure.
+    pub fn try_find(&mut self, element_id: usize) -> Result<usize, UnionFindError> {
+        self.find(element_id)
+            .ok_or_else(|| self.out_of_bounds(element_id))
+    }
+
+    fn out_of_bounds(&self, element_id: usize) -> UnionFindError {
+        UnionFindError::OutOfBounds {
+            element: element_id,
+            len: self.backing.len(),
+        }
+    }
+
+    fn find_full(&mut self, element_id: usize) -> usize {
+        let mut current = element_id;
+        // First, we loop through the pointer structure starting at our element_id
+        // and find the root, which is an element which points to itself.
+        loop {
+            let element = self.backing[current];
+            // If the current element's parent is equal to itself, it is by
+            // definition the root.
+            if element.parent == current {
+                break;
             }
-            Some(rep)
+            // Otherwise, we set current equal to the parent and continue
+            // the loop.
+            current = element.parent;
         }
+        let rep = current;
+        current = element_id;
+        // Next, we loop through the pointer structure again, updating each element
+        // on the way to point to the representative of our group. This way, in the
+        // future, this will complete much faster.
+        loop {
+            let element = self.backing[current];
+            // If the current node is equal to its parent, then we have
+            // reached the representative element for this set.
+            if current == element.parent {
+                break;
+            }
+            // Otherwise, we set the parent to be the representative element,
+            // maintaining the previous rank, update current to be equal to the
+            // parent, and continue the loop.
+            self.backing[current].parent = rep;
+            current = element.parent;
+        }
+        rep
     }
 
-    /// Cause the union of the sets which two elements belong to.
-    pub fn union(&mut self, element1: usize, element2: usize) {
-        if element1 >= self.backing.len() || element2 >= self.backing.len() {
-            return;
+    fn find_halving(&mut self, element_id: usize) -> usize {
+        let mut current = element_id;
+        // In a single pass, we point every other element on the path at its
+        // grandparent, jumping straight to that grandparent as we go.
+        loop {
+            let parent = self.backing[current].parent;
+            if parent == current {
+                break current;
+            }
+            let grandparent = self.backing[parent].parent;
+            self.backing[current].parent = grandparent;
+            current = grandparent;
+        }
+    }
+
+    fn find_splitting(&mut self, element_id: usize) -> usize {
+        let mut current = element_id;
+        // In a single pass, we point every element on the path at its
+        // grandparent, stepping to the old parent so that none are skipped.
+        loop {
+            let parent = self.backing[current].parent;
+            if parent == current {
+                break current;
+            }
+            let grandparent = self.backing[parent].parent;
+            self.backing[current].parent = grandparent;
+            current = parent;
+        }
+    }
+
+    fn find_uncompressed(&self, element_id: usize) -> usize {
+        let mut current = element_id;
+        loop {
+            let parent = self.backing[current].parent;
+            if parent == current {
+                break current;
+            }
+            current = parent;
+        }
+    }
+
+    /// Compute the number of elements in the set that this element belongs to.
+    pub fn size_of(&mut self, element_id: usize) -> Option<usize> {
+        let rep = self.find(element_id)?;
+        Some(self.backing[rep].size)
+    }
+
+    /// Iterate over every element in the set that this element belongs to, in
+    /// time proportional to the size of that set.
+    pub fn members(&self, element_id: usize) -> Option<Members<'_>> {
+        if element_id >= self.backing.len() {
+            None
         } else {
-            let rep1 = self.find(element1).unwrap();
-            let rep2 = self.find(element2).unwrap();
+            Some(Members::new(&self.backing, element_id))
+        }
+    }
 
-            if rep1 == rep2 {
-                return;
+    /// Compute a dense label in `0..set_count()` for every element, such that two
+    /// elements share a label exactly when they are in the same set. Labels are
+    /// handed out in order of the smallest element of each set.
+    pub fn labels(&self) -> Vec<usize> {
+        let mut labels = vec![usize::MAX; self.backing.len()];
+        let mut next_label = 0;
+        for element_id in 0..self.backing.len() {
+            if labels[element_id] == usize::MAX {
+                for member in Members::new(&self.backing, element_id) {
+                    labels[member] = next_label;
+                }
+                next_label += 1;
             }
+        }
+        labels
+    }
 
-            if self.backing[rep1].rank < self.backing[rep2].rank {
-                self.backing[rep1].parent = rep2;
-            } else if self.backing[rep1].rank > self.backing[rep2].rank {
-                self.backing[rep2].parent = rep1;
-            } else {
-                self.backing[rep1].parent = rep2;
-                self.backing[rep2].rank = self.backing[rep2].rank + 1;
+    /// Collect every set into a list of its elements. Each list is sorted, and the
+    /// lists are ordered by their smallest element, so that the group at index `i`
+    /// holds the elements labelled `i` by [`UnionFind::labels`].
+    pub fn groups(&self) -> Vec<Vec<usize>> {
+        let mut groups = vec![Vec::new(); self.set_count];
+        for (element_id, label) in self.labels().into_iter().enumerate() {
+            groups[label].push(element_id);
+        }
+        groups
+    }
+
+    /// Consume the structure, collecting every set as in [`UnionFind::groups`].
+    pub fn into_groups(self) -> Vec<Vec<usize>> {
+        self.groups()
+    }
+
+    /// Collect every set into a sorted list of its elements, keyed by its representative.
+    pub fn groups_by_representative(&self) -> HashMap<usize, Vec<usize>> {
+        let mut groups = HashMap::with_capacity(self.set_count);
+        for (element_id, element) in self.backing.iter().enumerate() {
+            if element.parent == element_id {
+                let mut members = Members::new(&self.backing, element_id).collect::<Vec<_>>();
+                members.sort_unstable();
+                groups.insert(element_id, members);
             }
         }
+        groups
+    }
+
+    /// The number of disjoint sets currently in the structure.
+    pub fn set_count(&self) -> usize {
+        self.set_count
+    }
+
+    /// Cause the union of the sets which two elements belong to, returning a
+    /// [`UnionOutcome`] describing what happened.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is not in the structure. See
+    /// [`UnionFind::try_union`] for a non-panicking version.
+    pub fn union(&mut self, element1: usize, element2: usize) -> UnionOutcome {
+        match self.try_union(element1, element2) {
+            Ok(outcome) => outcome,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Cause the union of the sets which two elements belong to, returning a
+    /// [`UnionOutcome`] describing what happened, or an error if either
+    /// element is not in the structure.
+    pub fn try_union(
+        &mut self,
+        element1: usize,
+        element2: usize,
+    ) -> Result<UnionOutcome, UnionFindError> {
+        self.try_union_if(element1, element2, |_, _| Ok(()))
+    }
+
+    /// Cause the union of the sets which two elements belong to, but only if
+    /// `allow`, given the representatives of the first and second element's
+    /// sets, approves of it. Returns a [`UnionOutcome`] describing what happened,
+    /// or the error from `allow` if it rejected the union, in which case no set
+    /// has changed.
+    ///
+    /// `allow` is not consulted if the two elements are already in the same set.
+    /// Elements which are not in the structure are reported through the
+    /// [`From<UnionFindError>`] implementation of the error type.
+    pub fn try_union_if<E: From<UnionFindError>>(
+        &mut self,
+        element1: usize,
+        element2: usize,
+        allow: impl FnOnce(usize, usize) -> Result<(), E>,
+    ) -> Result<UnionOutcome, E> {
+        let rep1 = self.try_find(element1)?;
+        let rep2 = self.try_find(element2)?;
+
+        if rep1 == rep2 {
+            Ok(UnionOutcome {
+                already_joined: true,
+                rep1,
+                rep2,
+                representative: rep1,
+                size: self.backing[rep1].size,
+            })
+        } else {
+            // Nothing but path compression has happened yet, so if the union
+            // is rejected here, every set is exactly as it was.
+            allow(rep1, rep2)?;
+
+            // Decide which of the two representatives survives. Unless the
+            // policy clearly prefers the first one, the second one wins.
+            let first_wins = match self.link_policy {
+                LinkPolicy::ByRank => self.backing[rep1].rank > self.backing[rep2].rank,
+                LinkPolicy::BySize => self.backing[rep1].size > self.backing[rep2].size,
+                LinkPolicy::Randomized(seed) => {
+                    strategy::priority(seed, rep1) > strategy::priority(seed, rep2)
+                }
+                LinkPolicy::ByIndex => rep1 < rep2,
+            };
+
+            let representative = if first_wins {
+                Element::link(&mut self.backing, rep2, rep1);
+                rep1
+            } else {
+                Element::link(&mut self.backing, rep1, rep2);
+                rep2
+            };
+            self.set_count -= 1;
+            Ok(UnionOutcome {
+                already_joined: false,
+                rep1,
+                rep2,
+                representative,
+                size: self.backing[representative].size,
+            })
+        }
+    }
+}
+
+impl Extend<(usize, usize)> for UnionFind {
+    /// Cause the union of the two ends of every edge, growing the structure
+    /// with fresh elements whenever an edge mentions one it does not yet hold.
+    fn extend<I: IntoIterator<Item = (usize, usize)>>(&mut self, edges: I) {
+        for (element1, element2) in edges {
+            let len = element1.max(element2).checked_add(1);
+            self.grow_to(len.expect("capacity overflow"));
+            self.union(element1, element2);
+        }
+    }
+}
+
+impl FromIterator<(usize, usize)> for UnionFind {
+    /// Build a structure holding just enough elements for every edge, with the
+    /// two ends of every edge in the same set.
+    fn from_iter<I: IntoIterator<Item = (usize, usize)>>(edges: I) -> Self {
+        let mut uf = UnionFind::new(0);
+        uf.extend(edges);
+        uf
     }
 }
 
+/// Compute the connected components of a graph over the given number of
+/// vertices, grouped as in [`UnionFind::groups`]. Vertices mentioned by an edge
+/// but beyond the given number are added to the graph.
+pub fn connected_components<I>(len: usize, edges: I) -> Vec<Vec<usize>>
+where
+    I: IntoIterator<Item = (usize, usize)>,
+{
+    let mut uf = UnionFind::new(len);
+    uf.extend(edges);
+    uf.into_groups()
+}
+
+/// Compute the connected components of a graph given by the neighbours of each
+/// of its vertices, grouped as in [`UnionFind::groups`]. Neighbours beyond the
+/// given vertices are added to the graph.
+pub fn connected_components_from_adjacency<A: AsRef<[usize]>>(adjacency: &[A]) -> Vec<Vec<usize>> {
+    connected_components(
+        adjacency.len(),
+        adjacency
+            .iter()
+            .enumerate()
+            .flat_map(|(vertex, neighbours)| {
+                neighbours
+                    .as_ref()
+                    .iter()
+                    .map(move |&neighbour| (vertex, neighbour))
+            }),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     const SIZE: usize = 10000;
 
     use super::*;
+    use crate::strategy::LINK_POLICIES;
+
+    fn each_strategy(mut test: impl FnMut(UnionFind)) {
+        for strategy in CompressionStrategy::ALL {
+            for policy in LINK_POLICIES {
+                test(
+                    UnionFind::new(SIZE)
+                        .with_compression(strategy)
+                        .with_link_policy(policy),
+                );
+            }
+        }
+    }
 
     #[test]
     fn union_two() {
-        let mut uf = UnionFind::new(SIZE);
-        uf.union(1, 2);
-        assert_eq!(uf.find(1).unwrap(), uf.find(2).unwrap());
+        each_strategy(|mut uf| {
+            uf.union(1, 2);
+            assert_eq!(uf.find(1).unwrap(), uf.find(2).unwrap());
+        });
     }
 
     #[test]
     fn union_all() {
-        let mut uf = UnionFind::new(SIZE);
-        for i in 0..SIZE {
-            uf.union(i, i + 1 % SIZE);
-        }
-        let rep = uf.find(0).unwrap();
-        for i in 0..SIZE {
-            assert_eq!(uf.find(i).unwrap(), rep);
-        }
+        each_strategy(|mut uf| {
+            for i in 0..SIZE {
+                uf.union(i, (i + 1) % SIZE);
+            }
+            let rep = uf.find(0).unwrap();
+            for i in 0..SIZE {
+                assert_eq!(uf.find(i).unwrap(), rep);
+            }
+        });
     }
 
     #[test]
     fn union_none() {
-        let mut uf = UnionFind::new(SIZE);
-        let rep = uf.find(0).unwrap();
-        for i in 1..SIZE {
-            assert_ne!(uf.find(i).unwrap(), rep);
-        }
+        each_strategy(|mut uf| {
+            let rep = uf.find(0).unwrap();
+            for i in 1..SIZE {
+                assert_ne!(uf.find(i).unwrap(), rep);
+            }
+        });
     }
 
     #[test]
     fn union_evens() {
-        let mut uf = UnionFind::new(SIZE);
-        for i in 0..SIZE {
-            uf.union(2 * i, 2 * (i + 1) % SIZE);
-        }
-        let rep = uf.find(0).unwrap();
-        for i in 0..SIZE {
-            if i % 2 == 0 {
+        each_strategy(|mut uf| {
+            for i in 0..SIZE / 2 {
+                uf.union(2 * i, 2 * (i + 1) % SIZE);
+            }
+            let rep = uf.find(0).unwrap();
+            for i in 0..SIZE {
+                if i % 2 == 0 {
+                    assert_eq!(uf.find(i).unwrap(), rep);
+                } else {
+                    assert_ne!(uf.find(i).unwrap(), rep);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn union_pairs_of_pairs() {
+        each_strategy(|mut uf| {
+            // Merging trees of equal rank repeatedly builds paths several hops long.
+            let mut width = 1;
+            while width < SIZE {
+                for i in (0..SIZE - width).step_by(2 * width) {
+                    uf.union(i, i + width);
+                }
+                width *= 2;
+            }
+            let rep = uf.find(0).unwrap();
+            for i in 0..SIZE {
                 assert_eq!(uf.find(i).unwrap(), rep);
-            } else {
-                assert_ne!(uf.find(i).unwrap(), rep);
             }
+        });
+    }
+
+    #[test]
+    fn smaller_index_wins() {
+        let mut uf = UnionFind::new(SIZE).with_link_policy(LinkPolicy::ByIndex);
+        uf.union(7, 3);
+        uf.union(9, 7);
+        uf.union(5, 1);
+        uf.union(9, 5);
+        for i in [1, 3, 5, 7, 9] {
+            assert_eq!(uf.find(i).unwrap(), 1);
         }
     }
+
+    #[test]
+    fn larger_set_wins() {
+        let mut uf = UnionFind::new(SIZE).with_link_policy(LinkPolicy::BySize);
+        for i in 1..5 {
+            uf.union(0, i);
+        }
+        let big = uf.find(0).unwrap();
+        uf.union(10, 11);
+        uf.union(10, 0);
+        assert_eq!(uf.find(10).unwrap(), big);
+    }
+
+    #[test]
+    fn randomized_is_deterministic() {
+        let run = |seed| {
+            let mut uf = UnionFind::new(SIZE).with_link_policy(LinkPolicy::Randomized(seed));
+            for i in 0..SIZE / 2 {
+                uf.union(i, SIZE - 1 - i);
+            }
+            (0..SIZE).map(|i| uf.find(i).unwrap()).collect::<Vec<_>>()
+        };
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1), run(2));
+    }
+
+    #[test]
+    fn sizes() {
+        each_strategy(|mut uf| {
+            assert_eq!(uf.size_of(0), Some(1));
+            assert_eq!(uf.size_of(SIZE), None);
+            for i in 0..10 {
+                uf.union(i, i + 1);
+            }
+            uf.union(20, 21);
+            uf.union(21, 20);
+            for i in 0..=10 {
+                assert_eq!(uf.size_of(i), Some(11));
+            }
+            assert_eq!(uf.size_of(20), Some(2));
+            let fresh = uf.fresh();
+            assert_eq!(uf.size_of(fresh), Some(1));
+            uf.union(fresh, 20);
+            assert_eq!(uf.size_of(21), Some(3));
+        });
+    }
+
+    #[test]
+    fn set_counts() {
+        each_strategy(|mut uf| {
+            assert_eq!(uf.set_count(), SIZE);
+            assert!(!uf.union(0, 1).already_joined);
+            assert!(uf.union(1, 0).already_joined);
+            assert_eq!(uf.set_count(), SIZE - 1);
+            uf.fresh();
+            assert_eq!(uf.set_count(), SIZE);
+            for i in 0..SIZE {
+                uf.union(i, SIZE);
+            }
+            assert_eq!(uf.set_count(), 1);
+        });
+    }
+
+    #[test]
+    fn members() {
+        each_strategy(|mut uf| {
+            assert!(uf.members(SIZE).is_none());
+            assert_eq!(uf.members(4).unwrap().collect::<Vec<_>>(), vec![4]);
+            for i in (0..20).step_by(2) {
+                uf.union(i, i + 2);
+            }
+            uf.union(1, 3);
+            let mut evens = uf.members(6).unwrap().collect::<Vec<_>>();
+            evens.sort();
+            assert_eq!(evens, (0..=20).step_by(2).collect::<Vec<_>>());
+            let mut odds = uf.members(3).unwrap().collect::<Vec<_>>();
+            odds.sort();
+            assert_eq!(odds, vec![1, 3]);
+            assert_eq!(uf.members(0).unwrap().len(), uf.size_of(0).unwrap());
+        });
+    }
+
+    #[test]
+    fn groups_and_labels() {
+        each_strategy(|mut uf| {
+            uf.union(5, 2);
+            uf.union(9, 2);
+            uf.union(3, 7);
+            let labels = uf.labels();
+            assert_eq!(&labels[..10], &[0, 1, 2, 3, 4, 2, 5, 3, 6, 2]);
+            assert_eq!(labels.iter().max(), Some(&(uf.set_count() - 1)));
+            let groups = uf.groups();
+            assert_eq!(groups.len(), uf.set_count());
+            assert_eq!(groups[2], vec![2, 5, 9]);
+            assert_eq!(groups[3], vec![3, 7]);
+            let by_rep = uf.groups_by_representative();
+            assert_eq!(by_rep.len(), uf.set_count());
+            assert_eq!(by_rep[&uf.find(9).unwrap()], vec![2, 5, 9]);
+            assert_eq!(uf.into_groups(), groups);
+        });
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        each_strategy(|mut uf| {
+            let err = UnionFindError::OutOfBounds {
+                element: SIZE,
+                len: SIZE,
+            };
+            assert_eq!(uf.try_find(SIZE), Err(err));
+            assert_eq!(uf.try_union(0, SIZE), Err(err));
+            assert_eq!(uf.try_union(SIZE, 0), Err(err));
+            assert!(!uf.try_union(0, 1).unwrap().already_joined);
+            assert!(uf.try_union(0, 1).unwrap().already_joined);
+            assert_eq!(uf.try_find(1), Ok(uf.find(0).unwrap()));
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn union_out_of_bounds_panics() {
+        let mut uf = UnionFind::new(SIZE);
+        uf.union(0, SIZE);
+    }
+
+    #[test]
+    fn union_outcomes() {
+        each_strategy(|mut uf| {
+            uf.union(0, 1);
+            uf.union(2, 3);
+            uf.union(3, 4);
+            let rep1 = uf.find(1).unwrap();
+            let rep2 = uf.find(4).unwrap();
+            let outcome = uf.union(1, 4);
+            assert!(!outcome.already_joined);
+            assert_eq!((outcome.rep1, outcome.rep2), (rep1, rep2));
+            assert!(outcome.representative == rep1 || outcome.representative == rep2);
+            assert_eq!(outcome.representative, uf.find(0).unwrap());
+            assert_eq!(outcome.size, 5);
+            let again = uf.union(2, 0);
+            assert!(again.already_joined);
+            assert_eq!(again.rep1, outcome.representative);
+            assert_eq!(again.rep2, outcome.representative);
+            assert_eq!(again.representative, outcome.representative);
+            assert_eq!(again.size, 5);
+        });
+    }
+
+    #[test]
+    fn immutable_queries() {
+        each_strategy(|mut uf| {
+            uf.union(0, 1);
+            uf.union(2, 3);
+            uf.union(1, 3);
+            let uf = &uf;
+            assert_eq!(uf.find_immutable(0), uf.find_immutable(3));
+            assert_eq!(uf.find_immutable(SIZE), None);
+            assert!(uf.same_set(0, 2));
+            assert!(!uf.same_set(0, 4));
+            assert!(!uf.same_set(0, SIZE));
+        });
+    }
+
+    #[test]
+    fn vetoed_unions() {
+        each_strategy(|mut uf| {
+            let frozen = 7;
+            let allow_unfrozen = |uf: &mut UnionFind, a, b| {
+                uf.try_union_if(a, b, |rep1, rep2| {
+                    if rep1 == frozen || rep2 == frozen {
+                        Err(UnionFindError::IncompatibleMerge {
+                            element1: a,
+                            element2: b,
+                        })
+                    } else {
+                        Ok(())
+                    }
+                })
+            };
+            assert!(allow_unfrozen(&mut uf, 0, 1).is_ok());
+            let ranks: Vec<_> = uf.backing.iter().map(|e| (e.rank, e.size)).collect();
+            assert_eq!(
+                allow_unfrozen(&mut uf, 1, 7),
+                Err(UnionFindError::IncompatibleMerge {
+                    element1: 1,
+                    element2: 7,
+                })
+            );
+            assert!(!uf.same_set(1, 7));
+            assert_eq!(uf.set_count(), SIZE - 1);
+            assert_eq!(
+                uf.backing
+                    .iter()
+                    .map(|e| (e.rank, e.size))
+                    .collect::<Vec<_>>(),
+                ranks
+            );
+            assert!(allow_unfrozen(&mut uf, 1, 0).unwrap().already_joined);
+            assert!(allow_unfrozen(&mut uf, 1, SIZE).is_err());
+        });
+    }
+
+    #[test]
+    fn edges_extend_and_collect() {
+        let mut uf: UnionFind = vec![(0, 1), (2, 3)].into_iter().collect();
+        assert_eq!(uf.set_count(), 2);
+        assert!(uf.same_set(0, 1));
+
+        uf.extend([(1, 5)]);
+        assert_eq!(uf.set_count(), 3);
+        assert_eq!(uf.groups(), vec![vec![0, 1, 5], vec![2, 3], vec![4]]);
+
+        let empty: UnionFind = std::iter::empty().collect();
+        assert_eq!(empty.set_count(), 0);
+    }
+
+    #[test]
+    fn connected_components_of_edges() {
+        assert_eq!(
+            connected_components(6, [(0, 2), (4, 2), (1, 3)]),
+            vec![vec![0, 2, 4], vec![1, 3], vec![5]]
+        );
+        assert_eq!(connected_components(1, [(2, 1)]), vec![vec![0], vec![1, 2]]);
+        assert!(connected_components(0, []).is_empty());
+    }
+
+    #[test]
+    fn connected_components_of_adjacency() {
+        let adjacency: Vec<Vec<usize>> = vec![vec![2], vec![3], vec![0, 4], vec![], vec![], vec![]];
+        assert_eq!(
+            connected_components_from_adjacency(&adjacency),
+            vec![vec![0, 2, 4], vec![1, 3], vec![5]]
+        );
+        assert_eq!(
+            connected_components_from_adjacency(&[&[1][..], &[], &[4]]),
+            vec![vec![0, 1], vec![2, 4], vec![3]]
+        );
+        let empty: [[usize; 0]; 0] = [];
+        assert!(connected_components_from_adjacency(&empty).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn huge_edges_fail_fast() {
+        let mut uf = UnionFind::new(1);
+        uf.extend([(0, usize::MAX / 2)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn largest_index_fails_fast() {
+        let mut uf = UnionFind::new(1);
+        uf.extend([(0, usize::MAX)]);
+    }
 }
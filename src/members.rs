@@ -0,0 +1,50 @@
+use std::iter::FusedIterator;
+
+use crate::Element;
+
+/// An iterator over the members of a single set, created by
+/// [`UnionFind::members`](crate::UnionFind::members).
+#[derive(Clone, Debug)]
+pub struct Members<'a> {
+    backing: &'a [Element],
+    start: usize,
+    current: Option<usize>,
+    remaining: usize,
+}
+
+impl<'a> Members<'a> {
+    pub(crate) fn new(backing: &'a [Element], start: usize) -> Self {
+        // The size is only kept at the representative, so we walk up to it
+        // without compressing anything to find out how many members remain.
+        let mut rep = start;
+        while backing[rep].parent != rep {
+            rep = backing[rep].parent;
+        }
+        Members {
+            backing,
+            start,
+            current: Some(start),
+            remaining: backing[rep].size,
+        }
+    }
+}
+
+impl Iterator for Members<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let current = self.current?;
+        let next = self.backing[current].next;
+        self.current = if next == self.start { None } else { Some(next) };
+        self.remaining -= 1;
+        Some(current)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Members<'_> {}
+
+impl FusedIterator for Members<'_> {}
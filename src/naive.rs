@@ -0,0 +1,328 @@
+/// A [`NaiveUnionFind`] maintains items indexed by natural numbers, each in a
+/// disjoint set, in the most obvious way possible: every element records the
+/// smallest element of its set, and a union relabels every element of the
+/// structure.
+///
+/// Every operation is easy to check by eye, at the cost of taking linear time,
+/// which makes this a reference model against which to test the other
+/// structures in this crate, rather than something to use in its own right.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NaiveUnionFind {
+    labels: Vec<usize>,
+}
+
+impl NaiveUnionFind {
+    /// Construct a new [`NaiveUnionFind`] with the given number of elements,
+    /// each in their own set.
+    pub fn new(size: usize) -> Self {
+        NaiveUnionFind {
+            labels: (0..size).collect(),
+        }
+    }
+
+    /// The number of elements in the structure.
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Whether the structure has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Add a new element to the structure in its own set, returning its index.
+    pub fn fresh(&mut self) -> usize {
+        let fresh = self.labels.len();
+        self.labels.push(fresh);
+        fresh
+    }
+
+    /// Find the smallest element of the set that this element belongs to.
+    pub fn find(&self, element_id: usize) -> Option<usize> {
+        self.labels.get(element_id).copied()
+    }
+
+    /// Whether two elements belong to the same set. Elements which are not in
+    /// the structure belong to no set.
+    pub fn same_set(&self, element1: usize, element2: usize) -> bool {
+        match (self.find(element1), self.find(element2)) {
+            (Some(label1), Some(label2)) => label1 == label2,
+            _ => false,
+        }
+    }
+
+    /// Compute the number of elements in the set that this element belongs to.
+    pub fn size_of(&self, element_id: usize) -> Option<usize> {
+        let label = self.find(element_id)?;
+        Some(self.labels.iter().filter(|&&other| other == label).count())
+    }
+
+    /// List every element in the set that this element belongs to, in order.
+    pub fn members(&self, element_id: usize) -> Option<Vec<usize>> {
+        let label = self.find(element_id)?;
+        Some(
+            (0..self.labels.len())
+                .filter(|&other| self.labels[other] == label)
+                .collect(),
+        )
+    }
+
+    /// The number of disjoint sets currently in the structure.
+    pub fn set_count(&self) -> usize {
+        self.labels
+            .iter()
+            .enumerate()
+            .filter(|&(element_id, &label)| element_id == label)
+            .count()
+    }
+
+    /// Collect every set into a sorted list of its elements, with the lists
+    /// ordered by their smallest element.
+    pub fn groups(&self) -> Vec<Vec<usize>> {
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut positions = vec![usize::MAX; self.labels.len()];
+        for (element_id, &label) in self.labels.iter().enumerate() {
+            // Every set is labelled by its smallest element, which is seen first.
+            if label == element_id {
+                positions[label] = groups.len();
+                groups.push(Vec::new());
+            }
+            groups[positions[label]].push(element_id);
+        }
+        groups
+    }
+
+    /// Cause the union of the sets which two elements belong to, returning
+    /// whether two distinct sets were actually merged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is not in the structure.
+    pub fn union(&mut self, element1: usize, element2: usize) -> bool {
+        let label1 = self.labels[element1];
+        let label2 = self.labels[element2];
+        if label1 == label2 {
+            return false;
+        }
+        let (kept, replaced) = (label1.min(label2), label1.max(label2));
+        for label in &mut self.labels {
+            if *label == replaced {
+                *label = kept;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use serde::de::{self, Deserializer};
+    use serde::Deserialize;
+
+    use super::NaiveUnionFind;
+
+    impl<'de> Deserialize<'de> for NaiveUnionFind {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            #[derive(Deserialize)]
+            struct NaiveRepr {
+                labels: Vec<usize>,
+            }
+            let labels = NaiveRepr::deserialize(deserializer)?.labels;
+            // Every element must be labelled by the smallest element of its
+            // set, which is labelled by itself.
+            for (element, &label) in labels.iter().enumerate() {
+                if label > element || labels[label] != label {
+                    return Err(de::Error::custom(format!(
+                        "element {element} is labelled by {label}, which does not label itself"
+                    )));
+                }
+            }
+            Ok(NaiveUnionFind { labels })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let mut naive = NaiveUnionFind::new(3);
+            naive.union(2, 1);
+            let json = serde_json::to_string(&naive).unwrap();
+            assert_eq!(
+                serde_json::from_str::<NaiveUnionFind>(&json).unwrap(),
+                naive
+            );
+        }
+
+        #[test]
+        fn invalid_labels_are_rejected() {
+            assert!(serde_json::from_str::<NaiveUnionFind>(r#"{"labels":[1,0]}"#).is_err());
+            assert!(serde_json::from_str::<NaiveUnionFind>(r#"{"labels":[0,0,1]}"#).is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::{priority, LINK_POLICIES};
+    use crate::{CompressionStrategy, LinkPolicy, UnionFind, UnionFindError};
+
+    /// A small deterministic source of randomness, so that failures reproduce.
+    struct Rng {
+        seed: u64,
+        count: usize,
+    }
+
+    impl Rng {
+        fn below(&mut self, bound: usize) -> usize {
+            self.count += 1;
+            (priority(self.seed, self.count) % bound as u64) as usize
+        }
+    }
+
+    /// Check that every query agrees between the structure and the model.
+    fn check_queries(uf: &mut UnionFind, model: &NaiveUnionFind, rng: &mut Rng) {
+        let len = model.len();
+        assert_eq!(uf.set_count(), model.set_count());
+        for _ in 0..8 {
+            let element1 = rng.below(len + 2);
+            let element2 = rng.below(len + 2);
+            assert_eq!(
+                uf.same_set(element1, element2),
+                model.same_set(element1, element2)
+            );
+            assert_eq!(uf.size_of(element1), model.size_of(element1));
+            let rep = uf.find_immutable(element1);
+            assert_eq!(uf.find(element1), rep);
+            assert_eq!(uf.try_find(element1).ok(), rep);
+            match rep {
+                Some(rep) => assert_eq!(model.find(rep), model.find(element1)),
+                None => assert!(model.find(element1).is_none()),
+            }
+            let members = uf.members(element1).map(|members| {
+                let mut members: Vec<_> = members.collect();
+                members.sort_unstable();
+                members
+            });
+            assert_eq!(members, model.members(element1));
+        }
+    }
+
+    /// Check that every way of listing the sets agrees between the structure
+    /// and the model.
+    fn check_groups(uf: &UnionFind, model: &NaiveUnionFind) {
+        let groups = model.groups();
+        assert_eq!(uf.groups(), groups);
+        let labels = uf.labels();
+        for (label, group) in groups.iter().enumerate() {
+            assert!(group.iter().all(|&element_id| labels[element_id] == label));
+        }
+        let mut by_representative: Vec<_> = uf
+            .groups_by_representative()
+            .into_iter()
+            .inspect(|(rep, group)| assert!(group.contains(rep)))
+            .map(|(_, group)| group)
+            .collect();
+        by_representative.sort_unstable();
+        assert_eq!(by_representative, groups);
+    }
+
+    fn differential(compression: CompressionStrategy, link_policy: LinkPolicy, seed: u64) {
+        let mut rng = Rng { seed, count: 0 };
+        let initial = rng.below(64);
+        let mut uf = UnionFind::new(initial)
+            .with_compression(compression)
+            .with_link_policy(link_policy);
+        let mut model = NaiveUnionFind::new(initial);
+        for _ in 0..500 {
+            let len = model.len();
+            // Elements are occasionally out of range, to exercise the errors.
+            let element1 = rng.below(len + 2);
+            let element2 = rng.below(len + 2);
+            let in_range = element1 < len && element2 < len;
+            match rng.below(10) {
+                0 => assert_eq!(uf.fresh(), model.fresh()),
+                1..=4 if in_range => {
+                    let rep1 = uf.find_immutable(element1).unwrap();
+                    let rep2 = uf.find_immutable(element2).unwrap();
+                    let outcome = uf.union(element1, element2);
+                    assert_eq!(outcome.already_joined, !model.union(element1, element2));
+                    assert_eq!((outcome.rep1, outcome.rep2), (rep1, rep2));
+                    assert!(outcome.representative == rep1 || outcome.representative == rep2);
+                    assert_eq!(uf.find(element1), Some(outcome.representative));
+                    assert_eq!(Some(outcome.size), model.size_of(element1));
+                }
+                1..=4 => {
+                    let err = uf.try_union(element1, element2).err().unwrap();
+                    let element = if element1 < len { element2 } else { element1 };
+                    assert_eq!(err, UnionFindError::OutOfBounds { element, len });
+                }
+                5 if in_range => {
+                    let veto = rng.below(2) == 0;
+                    let distinct = !model.same_set(element1, element2);
+                    let result = uf.try_union_if(element1, element2, |_, _| {
+                        if veto {
+                            Err(UnionFindError::IncompatibleMerge { element1, element2 })
+                        } else {
+                            Ok(())
+                        }
+                    });
+                    match result {
+                        Ok(outcome) => {
+                            assert!(!veto || !distinct);
+                            assert_eq!(outcome.already_joined, !model.union(element1, element2));
+                        }
+                        Err(_) => assert!(veto && distinct),
+                    }
+                }
+                6 => {
+                    let edges: Vec<_> = (0..rng.below(4))
+                        .map(|_| (rng.below(len + 2), rng.below(len + 2)))
+                        .collect();
+                    uf.extend(edges.iter().copied());
+                    for (element1, element2) in edges {
+                        while model.len() <= element1.max(element2) {
+                            model.fresh();
+                        }
+                        model.union(element1, element2);
+                    }
+                }
+                7 => check_groups(&uf, &model),
+                _ => check_queries(&mut uf, &model, &mut rng),
+            }
+        }
+        check_groups(&uf, &model);
+    }
+
+    #[test]
+    fn naive_union_find() {
+        let mut model = NaiveUnionFind::new(5);
+        assert!(model.union(3, 1));
+        assert!(model.union(4, 3));
+        assert!(!model.union(1, 4));
+        assert_eq!(model.find(4), Some(1));
+        assert_eq!(model.find(5), None);
+        assert_eq!(model.size_of(3), Some(3));
+        assert_eq!(model.set_count(), 3);
+        assert_eq!(model.fresh(), 5);
+        assert_eq!(
+            model.groups(),
+            vec![vec![0], vec![1, 3, 4], vec![2], vec![5]]
+        );
+    }
+
+    #[test]
+    fn union_find_matches_naive_model() {
+        for compression in CompressionStrategy::ALL {
+            for link_policy in LINK_POLICIES {
+                for seed in 0..4 {
+                    differential(compression, link_policy, seed);
+                }
+            }
+        }
+    }
+}
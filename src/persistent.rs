@@ -0,0 +1,435 @@
+use std::cell::RefCell;
+use std::mem;
+use std::rc::Rc;
+
+use crate::{Element, UnionFindError};
+
+/// A [`PersistentUnionFind`] maintains items indexed by natural numbers, each
+/// in a disjoint set, where [`PersistentUnionFind::union`] leaves the original
+/// structure untouched and returns a new version sharing most of its memory.
+///
+/// This follows "A Persistent Union-Find Data Structure" by Conchon and
+/// Filliâtre: elements live in a persistent array which is rerooted to
+/// whichever version is being accessed, so working with the most recent
+/// version costs about the same as working with a [`UnionFind`](crate::UnionFind).
+pub struct PersistentUnionFind {
+    backing: RefCell<PersistentArray<Element>>,
+    len: usize,
+    set_count: usize,
+}
+
+impl PersistentUnionFind {
+    /// Construct a new [`PersistentUnionFind`] with the given number of elements,
+    /// each in their own set.
+    pub fn new(size: usize) -> Self {
+        PersistentUnionFind {
+            backing: RefCell::new(PersistentArray::new(
+                (0..size)
+                    .map(|i| Element {
+                        parent: i,
+                        rank: 0,
+                        size: 1,
+                        next: i,
+                    })
+                    .collect(),
+            )),
+            len: size,
+            set_count: size,
+        }
+    }
+
+    /// The number of elements in the structure.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the structure has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of disjoint sets in this version of the structure.
+    pub fn set_count(&self) -> usize {
+        self.set_count
+    }
+
+    /// Compute a new version of the structure with a fresh element in its own
+    /// set, returning it along with the new element.
+    pub fn fresh(&self) -> (PersistentUnionFind, usize) {
+        let fresh = self.len;
+        let backing = self.backing.borrow().push(Element {
+            parent: fresh,
+            rank: 0,
+            size: 1,
+            next: fresh,
+        });
+        let version = PersistentUnionFind {
+            backing: RefCell::new(backing),
+            len: self.len + 1,
+            set_count: self.set_count + 1,
+        };
+        (version, fresh)
+    }
+
+    /// Find the representative for the set that this element belongs to.
+    ///
+    /// Paths are compressed along the way. This is invisible to every version
+    /// of the structure, but makes future calls on this version faster.
+    pub fn find(&self, element_id: usize) -> Option<usize> {
+        if element_id >= self.len {
+            return None;
+        }
+        let mut backing = self.backing.borrow_mut();
+        let mut current = element_id;
+        loop {
+            let parent = backing.get(current).parent;
+            if parent == current {
+                break;
+            }
+            current = parent;
+        }
+        let rep = current;
+        current = element_id;
+        while current != rep {
+            let mut element = backing.get(current);
+            let parent = element.parent;
+            if parent != rep {
+                element.parent = rep;
+                *backing = backing.set(current, element);
+            }
+            current = parent;
+        }
+        Some(rep)
+    }
+
+    /// Whether two elements belong to the same set. Elements which are not in
+    /// the structure belong to no set.
+    pub fn same_set(&self, element1: usize, element2: usize) -> bool {
+        match (self.find(element1), self.find(element2)) {
+            (Some(rep1), Some(rep2)) => rep1 == rep2,
+            _ => false,
+        }
+    }
+
+    /// Compute the number of elements in the set that this element belongs to.
+    pub fn size_of(&self, element_id: usize) -> Option<usize> {
+        let rep = self.find(element_id)?;
+        Some(self.backing.borrow_mut().get(rep).size)
+    }
+
+    /// Compute a new version of the structure in which the sets which two
+    /// elements belong to are merged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is not in the structure. See
+    /// [`PersistentUnionFind::try_union`] for a non-panicking version.
+    pub fn union(&self, element1: usize, element2: usize) -> PersistentUnionFind {
+        match self.try_union(element1, element2) {
+            Ok(union) => union,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Compute a new version of the structure in which the sets which two
+    /// elements belong to are merged, or an error if either element is not in
+    /// the structure.
+    pub fn try_union(
+        &self,
+        element1: usize,
+        element2: usize,
+    ) -> Result<PersistentUnionFind, UnionFindError> {
+        let out_of_bounds = |element| UnionFindError::OutOfBounds {
+            element,
+            len: self.len,
+        };
+        let rep1 = self.find(element1).ok_or_else(|| out_of_bounds(element1))?;
+        let rep2 = self.find(element2).ok_or_else(|| out_of_bounds(element2))?;
+        if rep1 == rep2 {
+            return Ok(self.clone());
+        }
+
+        let backing = self.backing.borrow_mut();
+        let root1 = backing.get(rep1);
+        let root2 = backing.get(rep2);
+        let (mut child, child_id, mut parent, parent_id) = if root1.rank > root2.rank {
+            (root2, rep2, root1, rep1)
+        } else {
+            (root1, rep1, root2, rep2)
+        };
+        Element::link_roots(&mut child, &mut parent, parent_id);
+
+        Ok(PersistentUnionFind {
+            backing: RefCell::new(backing.set(child_id, child).set(parent_id, parent)),
+            len: self.len,
+            set_count: self.set_count - 1,
+        })
+    }
+}
+
+impl Clone for PersistentUnionFind {
+    /// Cloning a version is cheap, as the clone shares all of its memory.
+    fn clone(&self) -> Self {
+        PersistentUnionFind {
+            backing: RefCell::new(self.backing.borrow().clone()),
+            len: self.len,
+            set_count: self.set_count,
+        }
+    }
+}
+
+/// A persistent array, represented as a single mutable array together with
+/// chains of differences leading to it from every other version.
+struct PersistentArray<T> {
+    node: Rc<RefCell<Node<T>>>,
+}
+
+enum Node<T> {
+    /// This version is the array itself.
+    Array(Vec<T>),
+    /// This version is the other version with a single index overwritten.
+    Diff(usize, T, PersistentArray<T>),
+    /// This version is the other version with a value pushed onto the end.
+    Push(T, PersistentArray<T>),
+    /// This version is the other version with its last value removed.
+    Pop(PersistentArray<T>),
+    /// A placeholder, only present while a version is being rewritten.
+    Empty,
+}
+
+impl<T> Drop for PersistentArray<T> {
+    fn drop(&mut self) {
+        // Dropping a long chain of differences recursively could overflow the
+        // stack, so we unlink the versions we hold the last reference to one by one.
+        if Rc::strong_count(&self.node) != 1 {
+            return;
+        }
+        let node = mem::replace(&mut *self.node.borrow_mut(), Node::Empty);
+        let Some(mut next) = node.into_next() else {
+            return;
+        };
+        while Rc::strong_count(&next.node) == 1 {
+            let node = mem::replace(&mut *next.node.borrow_mut(), Node::Empty);
+            let Some(following) = node.into_next() else {
+                return;
+            };
+            next = following;
+        }
+    }
+}
+
+impl<T> Node<T> {
+    /// The version this one is a difference from, if it is a difference at all.
+    fn into_next(self) -> Option<PersistentArray<T>> {
+        match self {
+            Node::Diff(_, _, next) | Node::Push(_, next) | Node::Pop(next) => Some(next),
+            Node::Array(_) | Node::Empty => None,
+        }
+    }
+}
+
+impl<T> Clone for PersistentArray<T> {
+    fn clone(&self) -> Self {
+        PersistentArray {
+            node: Rc::clone(&self.node),
+        }
+    }
+}
+
+impl<T: Clone> PersistentArray<T> {
+    fn new(array: Vec<T>) -> Self {
+        PersistentArray {
+            node: Rc::new(RefCell::new(Node::Array(array))),
+        }
+    }
+
+    /// Read the value at an index in this version.
+    fn get(&self, index: usize) -> T {
+        self.reroot();
+        match &*self.node.borrow() {
+            Node::Array(array) => array[index].clone(),
+            _ => unreachable!("rerooted versions hold the array"),
+        }
+    }
+
+    /// Compute a new version with the value at an index overwritten. Afterwards
+    /// the new version holds the array, and this version is a difference from it.
+    fn set(&self, index: usize, value: T) -> Self {
+        self.reroot();
+        let mut node = self.node.borrow_mut();
+        let Node::Array(mut array) = mem::replace(&mut *node, Node::Empty) else {
+            unreachable!("rerooted versions hold the array")
+        };
+        let old = mem::replace(&mut array[index], value);
+        let new = PersistentArray::new(array);
+        *node = Node::Diff(index, old, new.clone());
+        new
+    }
+
+    /// Compute a new version with a value pushed onto the end. Afterwards the
+    /// new version holds the array, and this version is a difference from it.
+    fn push(&self, value: T) -> Self {
+        self.reroot();
+        let mut node = self.node.borrow_mut();
+        let Node::Array(mut array) = mem::replace(&mut *node, Node::Empty) else {
+            unreachable!("rerooted versions hold the array")
+        };
+        array.push(value);
+        let new = PersistentArray::new(array);
+        *node = Node::Pop(new.clone());
+        new
+    }
+
+    /// Make this version hold the array, reversing every difference on the way.
+    fn reroot(&self) {
+        // We collect the chain of differences first, rather than recursing,
+        // so that long chains cannot overflow the stack.
+        let mut chain = vec![Rc::clone(&self.node)];
+        loop {
+            let next = match &*chain.last().unwrap().borrow() {
+                Node::Diff(_, _, next) | Node::Push(_, next) | Node::Pop(next) => {
+                    Rc::clone(&next.node)
+                }
+                Node::Array(_) | Node::Empty => break,
+            };
+            chain.push(next);
+        }
+        // Then, walking back from the array, we move the array one step closer
+        // to this version, turning the version it leaves into a difference.
+        for pair in chain.windows(2).rev() {
+            let (version, holder) = (&pair[0], &pair[1]);
+            let Node::Array(mut array) = mem::replace(&mut *holder.borrow_mut(), Node::Empty)
+            else {
+                unreachable!("the array was moved here by the previous step")
+            };
+            let back = PersistentArray {
+                node: Rc::clone(version),
+            };
+            let reversed = match mem::replace(&mut *version.borrow_mut(), Node::Empty) {
+                Node::Diff(index, value, _) => {
+                    let old = mem::replace(&mut array[index], value);
+                    Node::Diff(index, old, back)
+                }
+                Node::Push(value, _) => {
+                    array.push(value);
+                    Node::Pop(back)
+                }
+                Node::Pop(_) => Node::Push(array.pop().unwrap(), back),
+                Node::Array(_) | Node::Empty => {
+                    unreachable!("every version before the array is a difference")
+                }
+            };
+            *holder.borrow_mut() = reversed;
+            *version.borrow_mut() = Node::Array(array);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use std::cell::RefCell;
+
+    use serde::de::Deserializer;
+    use serde::ser::Serializer;
+    use serde::{Deserialize, Serialize};
+
+    use super::{PersistentArray, PersistentUnionFind};
+    use crate::serialize::ForestRepr;
+    use crate::Element;
+
+    impl Serialize for PersistentUnionFind {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let backing = self.backing.borrow();
+            ForestRepr::new((0..self.len).map(|i| backing.get(i))).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for PersistentUnionFind {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let backing = ForestRepr::deserialize(deserializer)?.rebuild()?;
+            Ok(PersistentUnionFind {
+                len: backing.len(),
+                set_count: Element::count_roots(&backing),
+                backing: RefCell::new(PersistentArray::new(backing)),
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let persistent = PersistentUnionFind::new(4).union(2, 3);
+            let persistent: PersistentUnionFind =
+                serde_json::from_str(&serde_json::to_string(&persistent).unwrap()).unwrap();
+            assert!(persistent.same_set(2, 3));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_independent() {
+        let v0 = PersistentUnionFind::new(10);
+        let v1 = v0.union(0, 1);
+        let v2 = v1.union(1, 2);
+        let v3 = v1.union(3, 4);
+        assert!(!v0.same_set(0, 1));
+        assert!(v1.same_set(0, 1));
+        assert!(!v1.same_set(1, 2));
+        assert!(v2.same_set(0, 2));
+        assert!(!v2.same_set(3, 4));
+        assert!(v3.same_set(3, 4));
+        assert!(!v3.same_set(0, 2));
+        assert_eq!(v0.set_count(), 10);
+        assert_eq!(v2.set_count(), 8);
+        assert_eq!(v3.set_count(), 8);
+        assert_eq!(v2.size_of(2), Some(3));
+        assert_eq!(v0.size_of(2), Some(1));
+    }
+
+    #[test]
+    fn fresh_elements_extend_one_version() {
+        let v0 = PersistentUnionFind::new(2).union(0, 1);
+        let (v1, fresh) = v0.fresh();
+        assert_eq!(fresh, 2);
+        let v2 = v1.union(fresh, 0);
+        let (v3, other) = v1.fresh();
+        assert_eq!(other, 3);
+        assert_eq!((v0.len(), v1.len(), v2.len(), v3.len()), (2, 3, 3, 4));
+        assert_eq!(v0.find(fresh), None);
+        assert_eq!(v1.set_count(), 2);
+        assert!(!v1.same_set(fresh, 0));
+        assert_eq!(v2.size_of(1), Some(3));
+        assert!(!v3.same_set(fresh, 0));
+        assert_eq!(v3.find(other), Some(other));
+        assert_eq!(v0.size_of(0), Some(2));
+        assert!(v2.same_set(2, 1));
+    }
+
+    #[test]
+    fn long_chains_of_versions() {
+        const SIZE: usize = 10000;
+        let first = PersistentUnionFind::new(SIZE);
+        let mut last = first.clone();
+        for i in 1..SIZE {
+            last = last.union(i - 1, i);
+        }
+        assert!(last.same_set(0, SIZE - 1));
+        assert_eq!(last.set_count(), 1);
+        assert!(!first.same_set(0, SIZE - 1));
+        assert!(last.same_set(0, SIZE - 1));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let uf = PersistentUnionFind::new(2);
+        assert_eq!(uf.find(2), None);
+        assert!(uf.try_union(0, 2).is_err());
+    }
+}
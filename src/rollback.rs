@@ -0,0 +1,291 @@
+use crate::{CompressionStrategy, Element, LinkPolicy, UnionFind, UnionFindError, UnionOutcome};
+
+/// A [`RollbackUnionFind`] is a [`UnionFind`] whose changes can be undone, as
+/// needed by backtracking search.
+///
+/// Paths are never compressed, so that every [`RollbackUnionFind::union`]
+/// changes exactly two elements, and sets are linked by rank, which keeps
+/// every path logarithmic in length without compression.
+pub struct RollbackUnionFind {
+    inner: UnionFind,
+    undo_log: Vec<Change>,
+    open_snapshots: usize,
+}
+
+/// A point to which a [`RollbackUnionFind`] may later be restored, created by
+/// [`RollbackUnionFind::snapshot`].
+///
+/// Snapshots must be rolled back to or committed in the reverse of the order
+/// in which they were taken.
+#[derive(Debug)]
+#[must_use = "a snapshot must be rolled back to or committed"]
+pub struct Snapshot {
+    undo_len: usize,
+    /// The number of snapshots open once this one was taken, which must be
+    /// the number still open when it is used.
+    depth: usize,
+}
+
+/// A change to the underlying [`UnionFind`] which may need to be undone.
+#[derive(Debug)]
+enum Change {
+    /// An element was added.
+    Fresh,
+    /// Two representatives were linked, and they previously looked like this.
+    Union {
+        child: (usize, Element),
+        parent: (usize, Element),
+    },
+}
+
+impl RollbackUnionFind {
+    /// Construct a new [`RollbackUnionFind`] with the given number of initial elements,
+    /// each in their own set.
+    pub fn new(size: usize) -> Self {
+        RollbackUnionFind {
+            inner: UnionFind::new(size)
+                .with_compression(CompressionStrategy::None)
+                .with_link_policy(LinkPolicy::ByRank),
+            undo_log: Vec::new(),
+            open_snapshots: 0,
+        }
+    }
+
+    /// Add a fresh element into the union find structure.
+    pub fn fresh(&mut self) -> usize {
+        self.record(Change::Fresh);
+        self.inner.fresh()
+    }
+
+    /// Find the representative for the set that this element belongs to.
+    pub fn find(&self, element_id: usize) -> Option<usize> {
+        self.inner.find_immutable(element_id)
+    }
+
+    /// Whether two elements belong to the same set. Elements which are not in
+    /// the structure belong to no set.
+    pub fn same_set(&self, element1: usize, element2: usize) -> bool {
+        self.inner.same_set(element1, element2)
+    }
+
+    /// Compute the number of elements in the set that this element belongs to.
+    pub fn size_of(&self, element_id: usize) -> Option<usize> {
+        let rep = self.find(element_id)?;
+        Some(self.inner.backing[rep].size)
+    }
+
+    /// The number of disjoint sets currently in the structure.
+    pub fn set_count(&self) -> usize {
+        self.inner.set_count()
+    }
+
+    /// Cause the union of the sets which two elements belong to, returning a
+    /// [`UnionOutcome`] describing what happened.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is not in the structure. See
+    /// [`RollbackUnionFind::try_union`] for a non-panicking version.
+    pub fn union(&mut self, element1: usize, element2: usize) -> UnionOutcome {
+        match self.try_union(element1, element2) {
+            Ok(outcome) => outcome,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Cause the union of the sets which two elements belong to, returning a
+    /// [`UnionOutcome`] describing what happened, or an error if either
+    /// element is not in the structure.
+    pub fn try_union(
+        &mut self,
+        element1: usize,
+        element2: usize,
+    ) -> Result<UnionOutcome, UnionFindError> {
+        // Without path compression, only the two representatives can change,
+        // so we remember what they looked like beforehand.
+        let before = |rep: Option<usize>| rep.map(|rep| (rep, self.inner.backing[rep]));
+        let before1 = before(self.find(element1));
+        let before2 = before(self.find(element2));
+        let outcome = self.inner.try_union(element1, element2)?;
+        if let (false, Some(before1), Some(before2)) = (outcome.already_joined, before1, before2) {
+            let (child, parent) = if outcome.representative == outcome.rep1 {
+                (before2, before1)
+            } else {
+                (before1, before2)
+            };
+            self.record(Change::Union { child, parent });
+        }
+        Ok(outcome)
+    }
+
+    /// Take a [`Snapshot`] of the current state of the structure.
+    pub fn snapshot(&mut self) -> Snapshot {
+        self.open_snapshots += 1;
+        Snapshot {
+            undo_len: self.undo_log.len(),
+            depth: self.open_snapshots,
+        }
+    }
+
+    /// Undo every change made since the [`Snapshot`] was taken.
+    pub fn rollback_to(&mut self, snapshot: Snapshot) {
+        self.assert_open(&snapshot);
+        while self.undo_log.len() > snapshot.undo_len {
+            match self.undo_log.pop().unwrap() {
+                Change::Fresh => {
+                    self.inner.backing.pop();
+                    self.inner.set_count -= 1;
+                }
+                Change::Union { child, parent } => {
+                    self.inner.backing[child.0] = child.1;
+                    self.inner.backing[parent.0] = parent.1;
+                    self.inner.set_count += 1;
+                }
+            }
+        }
+        self.open_snapshots -= 1;
+    }
+
+    /// Keep every change made since the [`Snapshot`] was taken. They may still be
+    /// undone by rolling back to an enclosing snapshot.
+    pub fn commit(&mut self, snapshot: Snapshot) {
+        self.assert_open(&snapshot);
+        self.open_snapshots -= 1;
+        if self.open_snapshots == 0 {
+            self.undo_log.clear();
+        }
+    }
+
+    fn record(&mut self, change: Change) {
+        // With no snapshots open, nothing can ever be undone.
+        if self.open_snapshots > 0 {
+            self.undo_log.push(change);
+        }
+    }
+
+    fn assert_open(&self, snapshot: &Snapshot) {
+        assert!(
+            snapshot.depth == self.open_snapshots,
+            "snapshots must be used in the reverse of the order they were taken"
+        );
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use serde::de::Deserializer;
+    use serde::ser::Serializer;
+    use serde::{Deserialize, Serialize};
+
+    use super::RollbackUnionFind;
+    use crate::serialize::ForestRepr;
+    use crate::Element;
+
+    impl Serialize for RollbackUnionFind {
+        /// Only the current state is serialized, including any changes made since
+        /// the open snapshots were taken, which can no longer be rolled back.
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            ForestRepr::new(self.inner.backing.iter().copied()).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for RollbackUnionFind {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let backing = ForestRepr::deserialize(deserializer)?.rebuild()?;
+            let mut rollback = RollbackUnionFind::new(0);
+            rollback.inner.set_count = Element::count_roots(&backing);
+            rollback.inner.backing = backing;
+            Ok(rollback)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let mut rollback = RollbackUnionFind::new(4);
+            rollback.union(0, 1);
+            let mut rollback: RollbackUnionFind =
+                serde_json::from_str(&serde_json::to_string(&rollback).unwrap()).unwrap();
+            let snapshot = rollback.snapshot();
+            rollback.union(1, 2);
+            rollback.rollback_to(snapshot);
+            assert!(rollback.same_set(0, 1));
+            assert!(!rollback.same_set(1, 2));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rollback_undoes_unions() {
+        let mut uf = RollbackUnionFind::new(10);
+        uf.union(0, 1);
+        let snapshot = uf.snapshot();
+        uf.union(1, 2);
+        uf.union(3, 4);
+        uf.union(4, 0);
+        assert!(uf.same_set(2, 3));
+        assert_eq!(uf.set_count(), 6);
+        uf.rollback_to(snapshot);
+        assert!(uf.same_set(0, 1));
+        assert!(!uf.same_set(1, 2));
+        assert!(!uf.same_set(3, 4));
+        assert_eq!(uf.size_of(0), Some(2));
+        assert_eq!(uf.size_of(3), Some(1));
+        assert_eq!(uf.set_count(), 9);
+    }
+
+    #[test]
+    fn nested_snapshots() {
+        let mut uf = RollbackUnionFind::new(4);
+        let outer = uf.snapshot();
+        uf.union(0, 1);
+        let inner = uf.snapshot();
+        let fresh = uf.fresh();
+        uf.union(fresh, 2);
+        uf.commit(inner);
+        assert!(uf.same_set(2, 4));
+        let inner = uf.snapshot();
+        uf.union(2, 3);
+        uf.rollback_to(inner);
+        assert!(!uf.same_set(2, 3));
+        assert!(uf.same_set(2, 4));
+        uf.rollback_to(outer);
+        assert_eq!(uf.find(4), None);
+        assert!(!uf.same_set(0, 1));
+        assert_eq!(uf.set_count(), 4);
+    }
+
+    #[test]
+    fn commit_forgets_changes() {
+        let mut uf = RollbackUnionFind::new(4);
+        let snapshot = uf.snapshot();
+        uf.union(0, 1);
+        uf.commit(snapshot);
+        assert!(uf.undo_log.is_empty());
+        let snapshot = uf.snapshot();
+        uf.union(2, 3);
+        uf.rollback_to(snapshot);
+        assert!(uf.same_set(0, 1));
+        assert!(!uf.same_set(2, 3));
+    }
+
+    #[test]
+    #[should_panic(expected = "reverse of the order")]
+    fn snapshots_out_of_order_panic() {
+        let mut uf = RollbackUnionFind::new(6);
+        let s1 = uf.snapshot();
+        uf.union(0, 1);
+        let s2 = uf.snapshot();
+        uf.union(2, 3);
+        uf.rollback_to(s1);
+        uf.union(4, 5);
+        uf.rollback_to(s2);
+    }
+}
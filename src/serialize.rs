@@ -0,0 +1,125 @@
+//! [`Serialize`] and [`Deserialize`] implementations for every structure in
+//! this crate, enabled by the `serde` feature.
+//!
+//! Structures are stored as their parent pointers and ranks, from which
+//! everything else is recomputed on deserialization. Parent pointers which
+//! are out of bounds or form cycles, and ranks which do not increase towards
+//! the root, are rejected, so a corrupted input can never make `find` hang.
+
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+use crate::{CompressionStrategy, Element, LinkPolicy, UnionFind};
+
+#[derive(Serialize, Deserialize)]
+struct UnionFindRepr {
+    parents: Vec<usize>,
+    ranks: Vec<usize>,
+    #[serde(default)]
+    compression: CompressionStrategy,
+    #[serde(default)]
+    link_policy: LinkPolicy,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ForestRepr {
+    pub(crate) parents: Vec<usize>,
+    pub(crate) ranks: Vec<usize>,
+}
+
+impl ForestRepr {
+    pub(crate) fn new(backing: impl Iterator<Item = Element>) -> Self {
+        let (parents, ranks) = backing.map(|e| (e.parent, e.rank)).unzip();
+        ForestRepr { parents, ranks }
+    }
+
+    pub(crate) fn rebuild<E: de::Error>(self) -> Result<Vec<Element>, E> {
+        Element::forest(self.parents, Some(self.ranks)).map_err(E::custom)
+    }
+}
+
+impl Serialize for UnionFind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let ForestRepr { parents, ranks } = ForestRepr::new(self.backing.iter().copied());
+        UnionFindRepr {
+            parents,
+            ranks,
+            compression: self.compression,
+            link_policy: self.link_policy,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for UnionFind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = UnionFindRepr::deserialize(deserializer)?;
+        let backing = ForestRepr {
+            parents: repr.parents,
+            ranks: repr.ranks,
+        }
+        .rebuild()?;
+        Ok(UnionFind {
+            set_count: Element::count_roots(&backing),
+            backing,
+            compression: repr.compression,
+            link_policy: repr.link_policy,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UnionFindError;
+
+    fn sample() -> UnionFind {
+        let mut uf = UnionFind::new(8).with_compression(CompressionStrategy::None);
+        uf.union(0, 1);
+        uf.union(2, 3);
+        uf.union(0, 2);
+        uf.union(5, 6);
+        uf
+    }
+
+    #[test]
+    fn union_find_round_trip() {
+        let uf = sample();
+        let json = serde_json::to_string(&uf).unwrap();
+        let mut back: UnionFind = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.compression(), CompressionStrategy::None);
+        assert_eq!(back.set_count(), uf.set_count());
+        assert_eq!(back.groups(), uf.groups());
+        assert_eq!(back.size_of(3), Some(4));
+        assert_eq!(back.members(6).unwrap().len(), 2);
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+    }
+
+    #[test]
+    fn corrupted_input_is_rejected() {
+        let error = |json: &str| {
+            serde_json::from_str::<UnionFind>(json)
+                .err()
+                .unwrap()
+                .to_string()
+        };
+        assert!(error(r#"{"parents":[0,5],"ranks":[1,0]}"#).starts_with(
+            &UnionFindError::InvalidParent {
+                element: 1,
+                parent: 5
+            }
+            .to_string()
+        ));
+        assert!(error(r#"{"parents":[1,2,0],"ranks":[0,1,2]}"#)
+            .starts_with(&UnionFindError::Cycle { element: 0 }.to_string()));
+        assert!(error(r#"{"parents":[1,1],"ranks":[1,1]}"#)
+            .starts_with(&UnionFindError::InvalidRank { element: 0 }.to_string()));
+        assert!(error(r#"{"parents":[1,1],"ranks":[0]}"#)
+            .starts_with(&UnionFindError::InvalidRank { element: 1 }.to_string()));
+        assert!(error(
+            r#"{"parents":[0,1],"ranks":[0,18446744073709551615],"link_policy":"BySize"}"#
+        )
+        .starts_with(&UnionFindError::InvalidRank { element: 1 }.to_string()));
+    }
+}
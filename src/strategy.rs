@@ -0,0 +1,61 @@
+/// The way in which [`UnionFind::find`](crate::UnionFind::find) shortens the
+/// paths it walks on the way to a representative.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CompressionStrategy {
+    /// Walk to the representative, then point every element on the path
+    /// directly at it.
+    #[default]
+    Full,
+    /// Point every other element on the path at its grandparent, in a single pass.
+    Halving,
+    /// Point every element on the path at its grandparent, in a single pass.
+    Splitting,
+    /// Leave the paths exactly as they are.
+    None,
+}
+
+impl CompressionStrategy {
+    /// Every available [`CompressionStrategy`].
+    pub const ALL: [CompressionStrategy; 4] = [
+        CompressionStrategy::Full,
+        CompressionStrategy::Halving,
+        CompressionStrategy::Splitting,
+        CompressionStrategy::None,
+    ];
+}
+
+/// The way in which [`UnionFind::union`](crate::UnionFind::union) decides
+/// which of two representatives becomes the representative of the merged set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LinkPolicy {
+    /// The representative whose tree has the greater rank wins.
+    #[default]
+    ByRank,
+    /// The representative of the set with more elements wins.
+    BySize,
+    /// Each element is given a pseudorandom priority derived from the seed,
+    /// and the representative with the greater priority wins.
+    Randomized(u64),
+    /// The representative with the smaller index wins.
+    ByIndex,
+}
+
+/// One of every kind of [`LinkPolicy`], for tests to run over.
+#[cfg(test)]
+pub(crate) const LINK_POLICIES: [LinkPolicy; 4] = [
+    LinkPolicy::ByRank,
+    LinkPolicy::BySize,
+    LinkPolicy::Randomized(0x5eed),
+    LinkPolicy::ByIndex,
+];
+
+/// The pseudorandom priority of an element under [`LinkPolicy::Randomized`],
+/// computed with the SplitMix64 finalizer.
+pub(crate) fn priority(seed: u64, element: usize) -> u64 {
+    let mut z = seed.wrapping_add((element as u64).wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,620 @@
+//! First-order unification of terms with variables, as used for type inference
+//! in the style of Hindley and Milner, built on [`UnionFind`].
+//!
+//! Variables are elements of a [`UnionFind`], and unifying two variables merges
+//! their sets. The representative of each set may be bound to a constructor
+//! applied to further terms, and unification makes sure no variable is ever
+//! bound to a term containing itself:
+//!
+//! ```
+//! use union_find::unify::{Term, Unifier};
+//!
+//! let arrow = |from, to| Term::App("->", vec![from, to]);
+//! let int = Term::App("int", vec![]);
+//!
+//! let mut unifier = Unifier::new();
+//! let a = Term::Var(unifier.fresh());
+//! let b = Term::Var(unifier.fresh());
+//!
+//! unifier.unify(&arrow(a.clone(), b.clone()), &arrow(int.clone(), a.clone())).unwrap();
+//!
+//! assert_eq!(unifier.resolve(&b), int);
+//! assert!(unifier.unify(&a, &arrow(int.clone(), int.clone())).is_err());
+//! ```
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+
+use crate::UnionFind;
+
+/// A term, which is either a variable or a constructor applied to further terms.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Term<C> {
+    /// A variable, as created by [`Unifier::fresh`].
+    Var(usize),
+    /// A constructor applied to some arguments.
+    App(C, Vec<Term<C>>),
+}
+
+impl<C: fmt::Display> fmt::Display for Term<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Terms can be deeper than the stack, so we keep the pieces still to be
+        // written on a stack of our own.
+        enum Piece<'a, C> {
+            Term(&'a Term<C>),
+            Text(&'static str),
+        }
+        let mut pending = vec![Piece::Term(self)];
+        while let Some(piece) = pending.pop() {
+            match piece {
+                Piece::Text(text) => f.write_str(text)?,
+                Piece::Term(Term::Var(variable)) => write!(f, "?{variable}")?,
+                Piece::Term(Term::App(constructor, args)) if args.is_empty() => {
+                    write!(f, "{constructor}")?
+                }
+                Piece::Term(Term::App(constructor, args)) => {
+                    write!(f, "{constructor}(")?;
+                    pending.push(Piece::Text(")"));
+                    for (i, arg) in args.iter().enumerate().rev() {
+                        pending.push(Piece::Term(arg));
+                        if i > 0 {
+                            pending.push(Piece::Text(", "));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The ways in which two terms can fail to unify.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UnifyError<C> {
+    /// Two subterms apply different constructors, or the same constructor to
+    /// different numbers of arguments. Both are given fully resolved.
+    Mismatch {
+        /// The subterm from the first term.
+        left: Term<C>,
+        /// The subterm from the second term.
+        right: Term<C>,
+    },
+    /// A variable would have to be bound to a term containing itself, which is
+    /// given fully resolved.
+    Occurs {
+        /// The representative of the variable.
+        variable: usize,
+        /// The term it would be bound to.
+        term: Term<C>,
+    },
+    /// A variable was not created by this unifier.
+    UnknownVariable {
+        /// The unknown variable.
+        variable: usize,
+    },
+}
+
+impl<C: fmt::Display> fmt::Display for UnifyError<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnifyError::Mismatch { left, right } => {
+                write!(f, "cannot unify {left} with {right}")
+            }
+            UnifyError::Occurs { variable, term } => {
+                write!(f, "variable ?{variable} occurs in {term}")
+            }
+            UnifyError::UnknownVariable { variable } => {
+                write!(f, "variable ?{variable} is not known to the unifier")
+            }
+        }
+    }
+}
+
+impl<C: fmt::Debug + fmt::Display> Error for UnifyError<C> {}
+
+/// A set of variables, along with the substitution built up by unifying terms
+/// containing them.
+pub struct Unifier<C> {
+    union_find: UnionFind,
+    /// The term each set of variables is bound to, held only at its representative.
+    bindings: Vec<Option<Term<C>>>,
+}
+
+impl<C: Eq + Clone> Unifier<C> {
+    /// Construct a new [`Unifier`] with no variables.
+    pub fn new() -> Self {
+        Unifier {
+            union_find: UnionFind::new(0),
+            bindings: Vec::new(),
+        }
+    }
+
+    /// The number of variables created so far.
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    /// Whether no variables have been created yet.
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    /// Create a fresh variable, unbound and distinct from every other.
+    pub fn fresh(&mut self) -> usize {
+        self.bindings.push(None);
+        self.union_find.fresh()
+    }
+
+    /// Find the representative for the set of variables this one has been unified with.
+    pub fn find(&mut self, variable: usize) -> Option<usize> {
+        self.union_find.find(variable)
+    }
+
+    /// The term this variable is bound to, if any.
+    pub fn binding(&mut self, variable: usize) -> Option<&Term<C>> {
+        let rep = self.find(variable)?;
+        self.bindings[rep].as_ref()
+    }
+
+    /// Unify two terms, extending the substitution so that both resolve to the
+    /// same term, or report why that is impossible.
+    ///
+    /// Unification stops at the first conflict it finds, keeping whatever it
+    /// had learnt before then.
+    pub fn unify(&mut self, term1: &Term<C>, term2: &Term<C>) -> Result<(), UnifyError<C>> {
+        self.check_variables(term1)?;
+        self.check_variables(term2)?;
+        let mut pending = vec![(term1.clone(), term2.clone())];
+        while let Some((term1, term2)) = pending.pop() {
+            let (rep1, term1) = self.shallow_resolve(term1);
+            let (rep2, term2) = self.shallow_resolve(term2);
+            if rep1.is_some() && rep1 == rep2 {
+                // Both sides are the same set of variables, which has already
+                // been unified with itself.
+                continue;
+            }
+            match ((rep1, term1), (rep2, term2)) {
+                ((_, Term::Var(variable1)), (_, Term::Var(variable2))) => {
+                    // Neither variable is bound, so neither is their union.
+                    self.join(variable1, variable2);
+                }
+                ((_, Term::Var(variable)), (rep, term))
+                | ((rep, term), (_, Term::Var(variable))) => {
+                    if self.occurs(variable, &term) {
+                        return Err(UnifyError::Occurs {
+                            variable,
+                            term: self.resolve(&term),
+                        });
+                    }
+                    // A term which some set of variables is bound to is shared
+                    // by joining that set, rather than copied.
+                    match rep {
+                        Some(rep) => self.join(variable, rep),
+                        None => self.bindings[variable] = Some(term),
+                    }
+                }
+                (
+                    (rep1, Term::App(constructor1, args1)),
+                    (rep2, Term::App(constructor2, args2)),
+                ) => {
+                    if constructor1 != constructor2 || args1.len() != args2.len() {
+                        return Err(UnifyError::Mismatch {
+                            left: self.resolve(&Term::App(constructor1, args1)),
+                            right: self.resolve(&Term::App(constructor2, args2)),
+                        });
+                    }
+                    // Joining two bound sets before unifying their arguments
+                    // means each pair of sets is only ever unified once.
+                    if let (Some(rep1), Some(rep2)) = (rep1, rep2) {
+                        self.join(rep1, rep2);
+                    }
+                    pending.extend(args1.into_iter().zip(args2));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Fully substitute a term, replacing every bound variable by the term it is
+    /// bound to and every unbound variable by its representative. Variables
+    /// which were not created by this unifier are left as they are.
+    pub fn resolve(&mut self, term: &Term<C>) -> Term<C> {
+        enum Step<C> {
+            Visit(Term<C>),
+            Apply(C, usize),
+        }
+        // Arguments are resolved onto a stack of their own, from which each
+        // application takes them once all of them are done.
+        let mut pending = vec![Step::Visit(term.clone())];
+        let mut resolved = Vec::new();
+        while let Some(step) = pending.pop() {
+            match step {
+                Step::Visit(Term::Var(variable)) => match self.find(variable) {
+                    Some(rep) => match &self.bindings[rep] {
+                        Some(bound) => pending.push(Step::Visit(bound.clone())),
+                        None => resolved.push(Term::Var(rep)),
+                    },
+                    None => resolved.push(Term::Var(variable)),
+                },
+                Step::Visit(Term::App(constructor, args)) => {
+                    pending.push(Step::Apply(constructor, args.len()));
+                    pending.extend(args.into_iter().rev().map(Step::Visit));
+                }
+                Step::Apply(constructor, arity) => {
+                    let args = resolved.split_off(resolved.len() - arity);
+                    resolved.push(Term::App(constructor, args));
+                }
+            }
+        }
+        resolved.pop().unwrap()
+    }
+
+    /// Check that every variable in a term was created by this unifier.
+    fn check_variables(&self, term: &Term<C>) -> Result<(), UnifyError<C>> {
+        let mut pending = vec![term];
+        while let Some(term) = pending.pop() {
+            match term {
+                Term::Var(variable) if *variable >= self.bindings.len() => {
+                    return Err(UnifyError::UnknownVariable {
+                        variable: *variable,
+                    });
+                }
+                Term::Var(_) => {}
+                Term::App(_, args) => pending.extend(args),
+            }
+        }
+        Ok(())
+    }
+
+    /// Follow bindings until the term is either an application or an unbound
+    /// representative, along with the representative of the variable it was,
+    /// if any.
+    fn shallow_resolve(&mut self, term: Term<C>) -> (Option<usize>, Term<C>) {
+        match term {
+            Term::Var(variable) => {
+                let rep = self.find(variable).unwrap();
+                match &self.bindings[rep] {
+                    Some(bound) => (Some(rep), bound.clone()),
+                    None => (Some(rep), Term::Var(rep)),
+                }
+            }
+            term => (None, term),
+        }
+    }
+
+    /// Merge two distinct sets of variables, keeping the binding of the first if
+    /// it has one, and otherwise that of the second.
+    fn join(&mut self, rep1: usize, rep2: usize) {
+        let binding = self.bindings[rep1].take().or(self.bindings[rep2].take());
+        let representative = self.union_find.union(rep1, rep2).representative;
+        self.bindings[representative] = binding;
+    }
+
+    /// Whether an unbound representative occurs in a term, once substituted.
+    fn occurs(&self, variable: usize, term: &Term<C>) -> bool {
+        // Bindings may be shared by many terms, so each is only walked once.
+        let mut visited = HashSet::new();
+        let mut pending = vec![term];
+        while let Some(term) = pending.pop() {
+            match term {
+                Term::Var(other) => {
+                    let rep = self.union_find.find_immutable(*other).unwrap();
+                    if rep == variable {
+                        return true;
+                    }
+                    if visited.insert(rep) {
+                        pending.extend(&self.bindings[rep]);
+                    }
+                }
+                Term::App(_, args) => pending.extend(args),
+            }
+        }
+        false
+    }
+}
+
+impl<C: Eq + Clone> Default for Unifier<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use serde::de::{self, Deserializer};
+    use serde::ser::Serializer;
+    use serde::{Deserialize, Serialize};
+
+    use super::{Term, Unifier};
+    use crate::UnionFind;
+
+    #[derive(Serialize, Deserialize)]
+    struct UnifierRepr<C> {
+        union_find: UnionFind,
+        bindings: Vec<Option<Term<C>>>,
+    }
+
+    impl<C: Serialize> Serialize for Unifier<C> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            #[derive(Serialize)]
+            struct UnifierReprRef<'a, C> {
+                union_find: &'a UnionFind,
+                bindings: &'a [Option<Term<C>>],
+            }
+            UnifierReprRef {
+                union_find: &self.union_find,
+                bindings: &self.bindings,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, C: Deserialize<'de>> Deserialize<'de> for Unifier<C> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = UnifierRepr::<C>::deserialize(deserializer)?;
+            let len = repr.union_find.backing.len();
+            if repr.bindings.len() != len {
+                return Err(de::Error::custom(format!(
+                    "there are bindings for {} variables, rather than {len}",
+                    repr.bindings.len()
+                )));
+            }
+            // Each binding must be an application held at a representative, and
+            // we note which representatives occur in it.
+            let mut waiting = vec![0; len];
+            let mut users = vec![Vec::new(); len];
+            for (variable, binding) in repr.bindings.iter().enumerate() {
+                let Some(binding) = binding else {
+                    continue;
+                };
+                if repr.union_find.backing[variable].parent != variable
+                    || matches!(binding, Term::Var(_))
+                {
+                    return Err(de::Error::custom(format!(
+                        "variable {variable} is not a representative bound to an application"
+                    )));
+                }
+                let mut pending = vec![binding];
+                while let Some(term) = pending.pop() {
+                    match term {
+                        Term::Var(other) if *other >= len => {
+                            return Err(de::Error::custom(format!(
+                                "the binding of variable {variable} contains unknown variable {other}"
+                            )));
+                        }
+                        Term::Var(other) => {
+                            let rep = repr.union_find.find_immutable(*other).unwrap();
+                            waiting[variable] += 1;
+                            users[rep].push(variable);
+                        }
+                        Term::App(_, args) => pending.extend(args),
+                    }
+                }
+            }
+            // Every variable must resolve to a finite term, so we resolve those
+            // whose bindings only contain resolved variables, until none remain.
+            let mut ready: Vec<usize> = (0..len).filter(|&rep| waiting[rep] == 0).collect();
+            while let Some(rep) = ready.pop() {
+                for &user in &users[rep] {
+                    waiting[user] -= 1;
+                    if waiting[user] == 0 {
+                        ready.push(user);
+                    }
+                }
+            }
+            if let Some(variable) = waiting.iter().position(|&count| count > 0) {
+                return Err(de::Error::custom(format!(
+                    "variable {variable} would resolve to an infinite term"
+                )));
+            }
+            Ok(Unifier {
+                union_find: repr.union_find,
+                bindings: repr.bindings,
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let mut unifier = Unifier::new();
+            let (a, b) = (Term::Var(unifier.fresh()), Term::Var(unifier.fresh()));
+            let pair = Term::App("pair".to_string(), vec![b.clone(), b.clone()]);
+            unifier.unify(&a, &pair).unwrap();
+            let mut unifier: Unifier<String> =
+                serde_json::from_str(&serde_json::to_string(&unifier).unwrap()).unwrap();
+            let int = Term::App("int".to_string(), vec![]);
+            unifier.unify(&b, &int).unwrap();
+            assert_eq!(
+                unifier.resolve(&a),
+                Term::App("pair".to_string(), vec![int.clone(), int])
+            );
+        }
+
+        #[test]
+        fn corrupted_bindings_are_rejected() {
+            let corrupted = |bindings: &str| {
+                serde_json::from_str::<Unifier<char>>(&format!(
+                    r#"{{"union_find":{{"parents":[0,0],"ranks":[1,0]}},"bindings":{bindings}}}"#
+                ))
+                .is_err()
+            };
+            assert!(!corrupted(r#"[{"App":["f",[]]},null]"#));
+            assert!(corrupted(r#"[null]"#));
+            assert!(corrupted(r#"[null,{"App":["f",[]]}]"#));
+            assert!(corrupted(r#"[{"Var":1},null]"#));
+            assert!(corrupted(r#"[{"App":["f",[{"Var":2}]]},null]"#));
+            assert!(corrupted(r#"[{"App":["f",[{"Var":1}]]},null]"#));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(constructor: &'static str, args: Vec<Term<&'static str>>) -> Term<&'static str> {
+        Term::App(constructor, args)
+    }
+
+    fn list(element: Term<&'static str>) -> Term<&'static str> {
+        app("list", vec![element])
+    }
+
+    #[test]
+    fn unification_binds_variables() {
+        let mut unifier = Unifier::new();
+        let (a, b, c) = (unifier.fresh(), unifier.fresh(), unifier.fresh());
+        let (a, b, c) = (Term::Var(a), Term::Var(b), Term::Var(c));
+        unifier.unify(&a, &b).unwrap();
+        assert_eq!(unifier.resolve(&a), unifier.resolve(&b));
+        assert_eq!(unifier.binding(0), None);
+
+        unifier
+            .unify(
+                &app("pair", vec![b.clone(), c.clone()]),
+                &app("pair", vec![list(c.clone()), app("int", vec![])]),
+            )
+            .unwrap();
+        assert_eq!(unifier.resolve(&a), list(app("int", vec![])));
+        assert_eq!(unifier.binding(1), Some(&list(c.clone())));
+        assert_eq!(unifier.len(), 3);
+    }
+
+    #[test]
+    fn bound_variables_are_joined() {
+        let mut unifier = Unifier::new();
+        let (a, b) = (unifier.fresh(), unifier.fresh());
+        unifier.unify(&Term::Var(a), &app("int", vec![])).unwrap();
+        unifier.unify(&Term::Var(b), &app("int", vec![])).unwrap();
+        assert_ne!(unifier.find(a), unifier.find(b));
+        unifier.unify(&Term::Var(a), &Term::Var(b)).unwrap();
+        assert_eq!(unifier.find(a), unifier.find(b));
+        assert_eq!(unifier.binding(b), Some(&app("int", vec![])));
+    }
+
+    #[test]
+    fn shared_terms_are_unified_once() {
+        // Each variable is bound to a pair of the next, so the terms they
+        // resolve to double in size at every step.
+        const DEPTH: usize = 64;
+        let mut unifier = Unifier::new();
+        let mut chain = || {
+            let variables: Vec<_> = (0..=DEPTH).map(|_| unifier.fresh()).collect();
+            for pair in variables.windows(2) {
+                let next = Term::Var(pair[1]);
+                unifier
+                    .unify(&Term::Var(pair[0]), &app("pair", vec![next.clone(), next]))
+                    .unwrap();
+            }
+            variables
+        };
+        let (first, second) = (chain(), chain());
+        for _ in 0..2 {
+            unifier
+                .unify(&Term::Var(first[0]), &Term::Var(second[0]))
+                .unwrap();
+        }
+        for (&a, &b) in first.iter().zip(&second) {
+            assert_eq!(unifier.find(a), unifier.find(b));
+        }
+
+        // Binding a fresh variable walks every binding in the chain to check
+        // that the variable does not occur in it.
+        let fresh = Term::Var(unifier.fresh());
+        unifier
+            .unify(&fresh, &app("f", vec![Term::Var(first[0])]))
+            .unwrap();
+    }
+
+    #[test]
+    fn mismatches_are_reported_resolved() {
+        let mut unifier = Unifier::new();
+        let a = Term::Var(unifier.fresh());
+        unifier.unify(&a, &app("int", vec![])).unwrap();
+        assert_eq!(
+            unifier.unify(&list(a.clone()), &list(app("bool", vec![]))),
+            Err(UnifyError::Mismatch {
+                left: app("int", vec![]),
+                right: app("bool", vec![]),
+            })
+        );
+        let err = unifier
+            .unify(
+                &app("pair", vec![a.clone(), a.clone()]),
+                &app("pair", vec![a.clone()]),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot unify pair(int, int) with pair(int)"
+        );
+    }
+
+    #[test]
+    fn occurs_check() {
+        let mut unifier = Unifier::new();
+        let (a, b) = (unifier.fresh(), unifier.fresh());
+        unifier.unify(&Term::Var(b), &list(Term::Var(a))).unwrap();
+        let err = unifier
+            .unify(&Term::Var(a), &list(Term::Var(b)))
+            .unwrap_err();
+        let rep = unifier.find(a).unwrap();
+        assert_eq!(
+            err,
+            UnifyError::Occurs {
+                variable: rep,
+                term: list(list(Term::Var(rep))),
+            }
+        );
+        assert!(unifier.unify(&Term::Var(a), &Term::Var(b)).is_err());
+        assert!(unifier.unify(&Term::Var(a), &Term::Var(a)).is_ok());
+    }
+
+    /// Drop a term without recursing, as it may be deeper than the stack.
+    fn dismantle(term: Term<&'static str>) {
+        let mut pending = vec![term];
+        while let Some(term) = pending.pop() {
+            if let Term::App(_, args) = term {
+                pending.extend(args);
+            }
+        }
+    }
+
+    #[test]
+    fn deep_terms_do_not_overflow() {
+        const DEPTH: usize = 100_000;
+        let mut unifier = Unifier::new();
+        let variables: Vec<_> = (0..=DEPTH).map(|_| unifier.fresh()).collect();
+        // Binding each variable to a list of the next keeps every term shallow,
+        // and so each occurs check cheap, until the first is resolved.
+        for pair in variables.windows(2) {
+            unifier
+                .unify(&Term::Var(pair[0]), &list(Term::Var(pair[1])))
+                .unwrap();
+        }
+        let deep = unifier.resolve(&Term::Var(variables[0]));
+        let expected = "list(".repeat(DEPTH) + &format!("?{DEPTH}") + &")".repeat(DEPTH);
+        assert_eq!(deep.to_string(), expected);
+        assert!(unifier.check_variables(&deep).is_ok());
+        dismantle(deep);
+    }
+
+    #[test]
+    fn unknown_variables() {
+        let mut unifier = Unifier::new();
+        let a = Term::Var(unifier.fresh());
+        assert_eq!(
+            unifier.unify(&a, &list(Term::Var(4))),
+            Err(UnifyError::UnknownVariable { variable: 4 })
+        );
+        assert_eq!(unifier.resolve(&Term::Var(4)), Term::Var(4));
+        assert_eq!(unifier.resolve(&a), a);
+    }
+}
@@ -0,0 +1,371 @@
+use crate::{Element, UnionFindError, UnionOutcome};
+
+/// A group, whose elements describe how the values of two elements relate.
+///
+/// The operation need not be commutative, but it must be associative, and
+/// [`Group::identity`] and [`Group::inverse`] must behave accordingly.
+pub trait Group: Clone + PartialEq {
+    /// The element which leaves every other element unchanged.
+    fn identity() -> Self;
+    /// Combine two elements, `self` first.
+    fn op(&self, other: &Self) -> Self;
+    /// The element which combines with this one to give the identity.
+    fn inverse(&self) -> Self;
+}
+
+macro_rules! integer_group {
+    ($($t:ty),*) => {
+        $(
+            /// The integers modulo the size of the type, under addition.
+            impl Group for $t {
+                fn identity() -> Self {
+                    0
+                }
+
+                fn op(&self, other: &Self) -> Self {
+                    self.wrapping_add(*other)
+                }
+
+                fn inverse(&self) -> Self {
+                    self.wrapping_neg()
+                }
+            }
+        )*
+    };
+}
+
+integer_group!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Parity, under exclusive or.
+impl Group for bool {
+    fn identity() -> Self {
+        false
+    }
+
+    fn op(&self, other: &Self) -> Self {
+        self ^ other
+    }
+
+    fn inverse(&self) -> Self {
+        *self
+    }
+}
+
+/// A [`WeightedUnionFind`] maintains items indexed by natural numbers, each in
+/// a disjoint set, along with the relative values of every two elements in the
+/// same set, expressed as elements of a [`Group`].
+///
+/// ```
+/// use union_find::WeightedUnionFind;
+///
+/// let mut uf = WeightedUnionFind::<i64>::new(3);
+///
+/// // x0 = 5 + x1, and x1 = 2 + x2
+/// uf.union_with(0, 1, 5).unwrap();
+/// uf.union_with(1, 2, 2).unwrap();
+///
+/// assert_eq!(uf.diff(0, 2), Some(7));
+/// assert!(uf.union_with(2, 0, 1).is_err());
+/// ```
+pub struct WeightedUnionFind<G> {
+    backing: Vec<Element>,
+    /// The value of each element relative to its parent, so that
+    /// `value(x) = offsets[x] · value(parent(x))`.
+    offsets: Vec<G>,
+    set_count: usize,
+}
+
+impl<G: Group> WeightedUnionFind<G> {
+    /// Construct a new [`WeightedUnionFind`] with the given number of initial
+    /// elements, each in their own set.
+    pub fn new(size: usize) -> Self {
+        WeightedUnionFind {
+            backing: (0..size)
+                .map(|i| Element {
+                    parent: i,
+                    rank: 0,
+                    size: 1,
+                    next: i,
+                })
+                .collect(),
+            offsets: vec![G::identity(); size],
+            set_count: size,
+        }
+    }
+
+    /// Add a fresh element into the union find structure.
+    pub fn fresh(&mut self) -> usize {
+        let fresh = self.backing.len();
+        self.backing.push(Element {
+            parent: fresh,
+            rank: 0,
+            size: 1,
+            next: fresh,
+        });
+        self.offsets.push(G::identity());
+        self.set_count += 1;
+        fresh
+    }
+
+    /// Find the representative for the set that this element belongs to.
+    pub fn find(&mut self, element_id: usize) -> Option<usize> {
+        self.find_with_offset(element_id).map(|(rep, _)| rep)
+    }
+
+    /// Find the representative for the set that this element belongs to, along
+    /// with the value of the element relative to it.
+    fn find_with_offset(&mut self, element_id: usize) -> Option<(usize, G)> {
+        if element_id >= self.backing.len() {
+            return None;
+        }
+        // First, we walk up to the root, remembering the path we took.
+        let mut path = Vec::new();
+        let mut current = element_id;
+        while self.backing[current].parent != current {
+            path.push(current);
+            current = self.backing[current].parent;
+        }
+        let rep = current;
+        // Next, walking back down from the root, we point every element on the
+        // path at it, accumulating offsets so that each becomes relative to it.
+        for &element in path.iter().rev() {
+            let parent = self.backing[element].parent;
+            if parent != rep {
+                self.offsets[element] = self.offsets[element].op(&self.offsets[parent]);
+                self.backing[element].parent = rep;
+            }
+        }
+        let offset = if element_id == rep {
+            G::identity()
+        } else {
+            self.offsets[element_id].clone()
+        };
+        Some((rep, offset))
+    }
+
+    /// The value of the first element relative to the second, that is the `w`
+    /// for which `value(element1) = w · value(element2)`, or [`None`] if they
+    /// are not known to be related.
+    pub fn diff(&mut self, element1: usize, element2: usize) -> Option<G> {
+        let (rep1, offset1) = self.find_with_offset(element1)?;
+        let (rep2, offset2) = self.find_with_offset(element2)?;
+        if rep1 == rep2 {
+            Some(offset1.op(&offset2.inverse()))
+        } else {
+            None
+        }
+    }
+
+    /// Compute the number of elements in the set that this element belongs to.
+    pub fn size_of(&mut self, element_id: usize) -> Option<usize> {
+        let rep = self.find(element_id)?;
+        Some(self.backing[rep].size)
+    }
+
+    /// The number of disjoint sets currently in the structure.
+    pub fn set_count(&self) -> usize {
+        self.set_count
+    }
+
+    /// Record that `value(element1) = weight · value(element2)`, merging their
+    /// sets, and returning a [`UnionOutcome`] describing what happened.
+    ///
+    /// If the two elements are already related in a way which contradicts this,
+    /// nothing is changed and [`UnionFindError::IncompatibleMerge`] is returned.
+    pub fn union_with(
+        &mut self,
+        element1: usize,
+        element2: usize,
+        weight: G,
+    ) -> Result<UnionOutcome, UnionFindError> {
+        let out_of_bounds = |element, len| UnionFindError::OutOfBounds { element, len };
+        let len = self.backing.len();
+        let (rep1, offset1) = self
+            .find_with_offset(element1)
+            .ok_or_else(|| out_of_bounds(element1, len))?;
+        let (rep2, offset2) = self
+            .find_with_offset(element2)
+            .ok_or_else(|| out_of_bounds(element2, len))?;
+
+        if rep1 == rep2 {
+            return if offset1 == weight.op(&offset2) {
+                Ok(UnionOutcome {
+                    already_joined: true,
+                    rep1,
+                    rep2,
+                    representative: rep1,
+                    size: self.backing[rep1].size,
+                })
+            } else {
+                Err(UnionFindError::IncompatibleMerge { element1, element2 })
+            };
+        }
+
+        // value(rep1) = offset1⁻¹ · weight · offset2 · value(rep2)
+        let between = offset1.inverse().op(&weight).op(&offset2);
+        let representative = if self.backing[rep1].rank > self.backing[rep2].rank {
+            Element::link(&mut self.backing, rep2, rep1);
+            self.offsets[rep2] = between.inverse();
+            rep1
+        } else {
+            Element::link(&mut self.backing, rep1, rep2);
+            self.offsets[rep1] = between;
+            rep2
+        };
+        self.set_count -= 1;
+        Ok(UnionOutcome {
+            already_joined: false,
+            rep1,
+            rep2,
+            representative,
+            size: self.backing[representative].size,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use serde::de::{self, Deserializer};
+    use serde::ser::Serializer;
+    use serde::{Deserialize, Serialize};
+
+    use super::WeightedUnionFind;
+    use crate::serialize::ForestRepr;
+    use crate::Element;
+
+    #[derive(Serialize, Deserialize)]
+    struct WeightedRepr<G> {
+        parents: Vec<usize>,
+        ranks: Vec<usize>,
+        offsets: Vec<G>,
+    }
+
+    impl<G: Serialize> Serialize for WeightedUnionFind<G> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            #[derive(Serialize)]
+            struct WeightedReprRef<'a, G> {
+                parents: Vec<usize>,
+                ranks: Vec<usize>,
+                offsets: &'a [G],
+            }
+            let ForestRepr { parents, ranks } = ForestRepr::new(self.backing.iter().copied());
+            WeightedReprRef {
+                parents,
+                ranks,
+                offsets: &self.offsets,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, G: Deserialize<'de>> Deserialize<'de> for WeightedUnionFind<G> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = WeightedRepr::<G>::deserialize(deserializer)?;
+            if repr.offsets.len() != repr.parents.len() {
+                return Err(de::Error::custom(format!(
+                    "there are {} offsets for {} elements",
+                    repr.offsets.len(),
+                    repr.parents.len()
+                )));
+            }
+            let backing = ForestRepr {
+                parents: repr.parents,
+                ranks: repr.ranks,
+            }
+            .rebuild()?;
+            Ok(WeightedUnionFind {
+                set_count: Element::count_roots(&backing),
+                backing,
+                offsets: repr.offsets,
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let mut weighted = WeightedUnionFind::<i32>::new(3);
+            weighted.union_with(0, 1, 4).unwrap();
+            weighted.union_with(1, 2, 4).unwrap();
+            let mut weighted: WeightedUnionFind<i32> =
+                serde_json::from_str(&serde_json::to_string(&weighted).unwrap()).unwrap();
+            assert_eq!(weighted.diff(0, 2), Some(8));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_compose() {
+        let mut uf = WeightedUnionFind::<i32>::new(10);
+        for i in 0..9 {
+            uf.union_with(i, i + 1, 1).unwrap();
+        }
+        assert_eq!(uf.diff(0, 9), Some(9));
+        assert_eq!(uf.diff(9, 0), Some(-9));
+        assert_eq!(uf.diff(4, 4), Some(0));
+        assert!(uf.union_with(9, 3, -6).unwrap().already_joined);
+        assert_eq!(uf.size_of(5), Some(10));
+    }
+
+    #[test]
+    fn contradictions_are_rejected() {
+        let mut uf = WeightedUnionFind::<bool>::new(4);
+        uf.union_with(0, 1, true).unwrap();
+        uf.union_with(1, 2, true).unwrap();
+        assert_eq!(uf.diff(0, 2), Some(false));
+        assert_eq!(
+            uf.union_with(2, 0, true),
+            Err(UnionFindError::IncompatibleMerge {
+                element1: 2,
+                element2: 0
+            })
+        );
+        assert_eq!(uf.diff(0, 2), Some(false));
+        assert_eq!(uf.diff(0, 3), None);
+        assert_eq!(uf.set_count(), 2);
+    }
+
+    /// Permutations of three items, as an example of a non-commutative group.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Perm([usize; 3]);
+
+    impl Group for Perm {
+        fn identity() -> Self {
+            Perm([0, 1, 2])
+        }
+
+        fn op(&self, other: &Self) -> Self {
+            Perm([other.0[self.0[0]], other.0[self.0[1]], other.0[self.0[2]]])
+        }
+
+        fn inverse(&self) -> Self {
+            let mut inverse = [0; 3];
+            for (i, &j) in self.0.iter().enumerate() {
+                inverse[j] = i;
+            }
+            Perm(inverse)
+        }
+    }
+
+    #[test]
+    fn non_commutative_groups() {
+        let swap = Perm([1, 0, 2]);
+        let rotate = Perm([1, 2, 0]);
+        let mut uf = WeightedUnionFind::new(4);
+        uf.union_with(0, 1, swap.clone()).unwrap();
+        uf.union_with(2, 3, rotate.clone()).unwrap();
+        uf.union_with(1, 2, rotate.clone()).unwrap();
+        let expected = swap.op(&rotate).op(&rotate);
+        assert_eq!(uf.diff(0, 3), Some(expected.clone()));
+        assert_eq!(uf.diff(3, 0), Some(expected.inverse()));
+        assert!(uf.union_with(0, 3, expected).is_ok());
+        assert!(uf.union_with(0, 3, swap).is_err());
+    }
+}
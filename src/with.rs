@@ -0,0 +1,245 @@
+use crate::{UnionFind, UnionFindError, UnionOutcome};
+
+/// A way of merging the data associated with two sets when they are merged.
+///
+/// For the result not to depend on the order of unions, the merge should be
+/// associative, as in a monoid.
+pub trait Merge<V> {
+    /// Merge the data of the first set with the data of the second set.
+    fn merge(&mut self, first: V, second: V) -> V;
+}
+
+impl<V, F: FnMut(V, V) -> V> Merge<V> for F {
+    fn merge(&mut self, first: V, second: V) -> V {
+        self(first, second)
+    }
+}
+
+/// A [`UnionFindWith`] is a [`UnionFind`] which associates some data with
+/// each set, merging it with a [`Merge`] whenever two sets are merged.
+///
+/// ```
+/// use union_find::UnionFindWith;
+///
+/// let mut uf = UnionFindWith::new([3, 1, 4, 1, 5], |a: u32, b: u32| a.min(b));
+///
+/// uf.union(0, 2);
+/// uf.union(2, 4);
+///
+/// assert_eq!(uf.data(4), Some(&3));
+/// ```
+pub struct UnionFindWith<V, M> {
+    inner: UnionFind,
+    /// The data of each set, held only at its representative.
+    data: Vec<Option<V>>,
+    merge: M,
+}
+
+impl<V, M: Merge<V>> UnionFindWith<V, M> {
+    /// Construct a new [`UnionFindWith`] with one element for each of the given
+    /// values, each in their own set.
+    pub fn new(values: impl IntoIterator<Item = V>, merge: M) -> Self {
+        let data: Vec<Option<V>> = values.into_iter().map(Some).collect();
+        UnionFindWith {
+            inner: UnionFind::new(data.len()),
+            data,
+            merge,
+        }
+    }
+
+    /// Add a fresh element with the given value into the union find structure.
+    pub fn fresh(&mut self, value: V) -> usize {
+        self.data.push(Some(value));
+        self.inner.fresh()
+    }
+
+    /// Find the representative for the set that this element belongs to.
+    pub fn find(&mut self, element_id: usize) -> Option<usize> {
+        self.inner.find(element_id)
+    }
+
+    /// The data of the set that this element belongs to.
+    pub fn data(&self, element_id: usize) -> Option<&V> {
+        let rep = self.inner.find_immutable(element_id)?;
+        self.data[rep].as_ref()
+    }
+
+    /// The data of the set that this element belongs to, for modification.
+    pub fn data_mut(&mut self, element_id: usize) -> Option<&mut V> {
+        let rep = self.find(element_id)?;
+        self.data[rep].as_mut()
+    }
+
+    /// Compute the number of elements in the set that this element belongs to.
+    pub fn size_of(&mut self, element_id: usize) -> Option<usize> {
+        self.inner.size_of(element_id)
+    }
+
+    /// The number of disjoint sets currently in the structure.
+    pub fn set_count(&self) -> usize {
+        self.inner.set_count()
+    }
+
+    /// Cause the union of the sets which two elements belong to, merging the data
+    /// of the first element's set with the data of the second element's set, and
+    /// returning a [`UnionOutcome`] describing what happened.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is not in the structure. See
+    /// [`UnionFindWith::try_union`] for a non-panicking version.
+    pub fn union(&mut self, element1: usize, element2: usize) -> UnionOutcome {
+        match self.try_union(element1, element2) {
+            Ok(outcome) => outcome,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Cause the union of the sets which two elements belong to, merging the data
+    /// of the first element's set with the data of the second element's set, and
+    /// returning a [`UnionOutcome`] describing what happened, or an error if either
+    /// element is not in the structure.
+    pub fn try_union(
+        &mut self,
+        element1: usize,
+        element2: usize,
+    ) -> Result<UnionOutcome, UnionFindError> {
+        let outcome = self.inner.try_union(element1, element2)?;
+        if !outcome.already_joined {
+            let first = self.data[outcome.rep1].take().unwrap();
+            let second = self.data[outcome.rep2].take().unwrap();
+            self.data[outcome.representative] = Some(self.merge.merge(first, second));
+        }
+        Ok(outcome)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use serde::de::{self, Deserializer};
+    use serde::ser::Serializer;
+    use serde::{Deserialize, Serialize};
+
+    use super::UnionFindWith;
+    use crate::UnionFind;
+
+    #[derive(Serialize, Deserialize)]
+    struct WithRepr<V> {
+        union_find: UnionFind,
+        data: Vec<Option<V>>,
+    }
+
+    impl<V: Serialize, M> Serialize for UnionFindWith<V, M> {
+        /// The [`Merge`](crate::Merge) itself is not serialized.
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            #[derive(Serialize)]
+            struct WithReprRef<'a, V> {
+                union_find: &'a UnionFind,
+                data: &'a [Option<V>],
+            }
+            WithReprRef {
+                union_find: &self.inner,
+                data: &self.data,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, V: Deserialize<'de>, M: Default> Deserialize<'de> for UnionFindWith<V, M> {
+        /// The [`Merge`](crate::Merge) is constructed with its [`Default`] implementation.
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = WithRepr::<V>::deserialize(deserializer)?;
+            let backing = &repr.union_find.backing;
+            if repr.data.len() != backing.len() {
+                return Err(de::Error::custom(format!(
+                    "there is data for {} elements, rather than {}",
+                    repr.data.len(),
+                    backing.len()
+                )));
+            }
+            for (element, data) in repr.data.iter().enumerate() {
+                if data.is_some() != (backing[element].parent == element) {
+                    return Err(de::Error::custom(format!(
+                        "element {element} must have data exactly when it is a representative"
+                    )));
+                }
+            }
+            Ok(UnionFindWith {
+                inner: repr.union_find,
+                data: repr.data,
+                merge: M::default(),
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use crate::Merge;
+
+        #[derive(Default)]
+        struct Max;
+
+        impl Merge<u32> for Max {
+            fn merge(&mut self, first: u32, second: u32) -> u32 {
+                first.max(second)
+            }
+        }
+
+        #[test]
+        fn round_trip() {
+            let mut with = UnionFindWith::new([1, 5, 3], Max);
+            with.union(0, 1);
+            let mut with: UnionFindWith<u32, Max> =
+                serde_json::from_str(&serde_json::to_string(&with).unwrap()).unwrap();
+            with.union(2, 0);
+            assert_eq!(with.data(2), Some(&5));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_follows_representative() {
+        let mut uf = UnionFindWith::new((0..10).map(|i| vec![i]), |mut a: Vec<_>, b| {
+            a.extend(b);
+            a
+        });
+        uf.union(0, 1);
+        uf.union(2, 3);
+        uf.union(3, 1);
+        uf.union(0, 2);
+        assert_eq!(uf.data(1), Some(&vec![2, 3, 0, 1]));
+        uf.data_mut(3).unwrap().push(10);
+        assert_eq!(uf.data(0).unwrap().len(), 5);
+        assert_eq!(uf.data(4), Some(&vec![4]));
+        assert_eq!(uf.data(10), None);
+        assert_eq!(uf.set_count(), 7);
+        let shared = &uf;
+        assert_eq!(shared.data(2), shared.data(1));
+    }
+
+    struct Sum;
+
+    impl Merge<u64> for Sum {
+        fn merge(&mut self, first: u64, second: u64) -> u64 {
+            first + second
+        }
+    }
+
+    #[test]
+    fn fresh_elements_carry_data() {
+        let mut uf = UnionFindWith::new([], Sum);
+        let a = uf.fresh(5);
+        let b = uf.fresh(7);
+        let c = uf.fresh(11);
+        uf.union(a, b);
+        uf.union(c, a);
+        assert_eq!(uf.data(b), Some(&23));
+        assert_eq!(uf.size_of(c), Some(3));
+    }
+}